@@ -1,59 +1,391 @@
+use std::env;
 use advent_of_code_2023::common::read_valid_lines;
-use anyhow::{Result, Context};
-use regex::{Regex};
+use anyhow::{anyhow, Result, Context};
+use crate::diagnose::Diagnostics;
+use crate::matcher::DigitMatcher;
+use crate::vocabulary::Vocabulary;
 
 #[macro_use]
 extern crate simple_log;
 
-fn calibration_value_words(line: String) -> Result<u32> {
-    let translator = |num_str: &str| match num_str {
-        "one" => 1,
-        "two" => 2,
-        "three" => 3,
-        "four" => 4,
-        "five" => 5,
-        "six" => 6,
-        "seven" => 7,
-        "eight" => 8,
-        "nine" => 9,
-        "1" => 1,
-        "2" => 2,
-        "3" => 3,
-        "4" => 4,
-        "5" => 5,
-        "6" => 6,
-        "7" => 7,
-        "8" => 8,
-        "9" => 9,
-        other => panic!("Unexpected input: {}", other)
-    };
-
-    // replace first number word
-    let base_regex = r"(1|2|3|4|5|6|7|8|9|one|two|three|four|five|six|seven|eight|nine)";
-    let first = Regex::new(base_regex)
-        .expect("valid regex")
-        .captures(&line)
-        .and_then(|caps| caps.get(1))
-        .map(|mat| translator(&line[mat.range()]))
-        .context("not even one digit found")?;
-
-    let last = Regex::new(format!(".*{}", base_regex).as_str())
-        .expect("valid regex")
-        .captures(&line)
-        .and_then(|caps| caps.get(1))
-        .map(|met| translator(&line[met.range()]))
-        .unwrap_or(first);
+mod vocabulary {
+    use std::fs::read_to_string;
+    use anyhow::{bail, ensure, Context, Result};
 
-    Ok((first * 10) + last)
+    const ENGLISH: [&str; 9] = ["one", "two", "three", "four", "five", "six", "seven", "eight", "nine"];
+    const ENGLISH_TEENS: [&str; 11] = ["ten", "eleven", "twelve", "thirteen", "fourteen", "fifteen",
+        "sixteen", "seventeen", "eighteen", "nineteen", "twenty"];
+    const GERMAN: [&str; 9] = ["eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun"];
+    const FRENCH: [&str; 9] = ["un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf"];
+
+    /// All words that count as a number in a calibration document, together with their values.
+    /// Words may overlap each other (`twone`) or contain each other (`seventeen`), the matcher
+    /// takes care of that.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Vocabulary {
+        words: Vec<(String, u32)>,
+    }
+
+    impl Vocabulary {
+        /// Just the digits 1 to 9, as in part 1.
+        pub fn digits() -> Self {
+            Vocabulary { words: (1..=9).map(|digit| (digit.to_string(), digit)).collect() }
+        }
+
+        /// Digits, plus one, two, three, four, five, six, seven, eight, and nine.
+        pub fn english() -> Self {
+            Vocabulary::digits().with_words(&ENGLISH, 1)
+        }
+
+        /// Like [Vocabulary::english], plus everything from ten up to twenty.
+        pub fn english_to_twenty() -> Self {
+            Vocabulary::english().with_words(&ENGLISH_TEENS, 10)
+        }
+
+        pub fn german() -> Self {
+            Vocabulary::digits().with_words(&GERMAN, 1)
+        }
+
+        pub fn french() -> Self {
+            Vocabulary::digits().with_words(&FRENCH, 1)
+        }
+
+        /// Looks up a built-in vocabulary, optionally followed by `+zero`, e.g. `english+zero`.
+        pub fn by_name(name: &str) -> Result<Self> {
+            let (language, with_zero) = match name.strip_suffix("+zero") {
+                Some(language) => (language, true),
+                None => (name, false)
+            };
+            let (vocabulary, zero) = match language {
+                "digits" => (Vocabulary::digits(), "0"),
+                "english" => (Vocabulary::english(), "zero"),
+                "english-to-twenty" => (Vocabulary::english_to_twenty(), "zero"),
+                "german" => (Vocabulary::german(), "null"),
+                "french" => (Vocabulary::french(), "zéro"),
+                _ => bail!("unknown vocabulary '{}'", name)
+            };
+            Ok(if with_zero { vocabulary.with_zero(zero) } else { vocabulary })
+        }
+
+        /// Reads a word list with one `word value` pair per line on top of the digits. Empty
+        /// lines and lines starting with `#` are ignored.
+        pub fn from_file(filename: &str) -> Result<Self> {
+            let raw_data = read_to_string(filename)
+                .with_context(|| format!("can't read vocabulary {}", filename))?;
+
+            let mut vocabulary = Vocabulary::digits();
+            for (idx, line) in raw_data.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let (word, value) = line.split_once(char::is_whitespace)
+                    .with_context(|| format!("line {}: expected 'word value', got '{}'", idx + 1, line))?;
+                let value = value.trim().parse()
+                    .with_context(|| format!("line {}: '{}' is not a number", idx + 1, value.trim()))?;
+                vocabulary = vocabulary.with_word(word, value)?;
+            }
+            Ok(vocabulary)
+        }
+
+        /// Adds the digit 0 and the given word for it.
+        pub fn with_zero(self, word: &str) -> Self {
+            let mut words = self.words;
+            words.push(("0".to_string(), 0));
+            if word != "0" {
+                words.push((word.to_string(), 0));
+            }
+            Vocabulary { words }
+        }
+
+        pub fn with_word(mut self, word: &str, value: u32) -> Result<Self> {
+            ensure!(!word.is_empty(), "empty words can't be matched");
+            if let Some((_, known)) = self.words.iter().find(|(w, _)| w == word) {
+                ensure!(*known == value, "'{}' already means {}, not {}", word, known, value);
+                return Ok(self);
+            }
+            self.words.push((word.to_string(), value));
+            Ok(self)
+        }
+
+        fn with_words(mut self, words: &[&str], first_value: u32) -> Self {
+            self.words.extend(words.iter().zip(first_value..).map(|(word, value)| (word.to_string(), value)));
+            self
+        }
+
+        pub fn words(&self) -> impl Iterator<Item=&(String, u32)> {
+            self.words.iter()
+        }
+    }
+}
+
+mod matcher {
+    use crate::vocabulary::Vocabulary;
+
+    /// A number word (or digit) found in a line, `start..end` are byte positions.
+    #[derive(Debug, PartialEq, Clone)]
+    pub struct Match {
+        pub value: u32,
+        pub start: usize,
+        pub end: usize,
+    }
+
+    #[derive(Debug, Default)]
+    struct Node {
+        children: Vec<(u8, usize)>,
+        value: Option<u32>,
+    }
+
+    /// A trie of all words of a [Vocabulary]. To find the first word we try to match the trie at
+    /// every position starting from the front, for the last word starting from the back. That
+    /// way overlapping words like `twone` are found without any backtracking.
+    #[derive(Debug)]
+    pub struct DigitMatcher {
+        nodes: Vec<Node>,
+    }
+
+    impl DigitMatcher {
+        pub fn new(vocabulary: &Vocabulary) -> Self {
+            let mut nodes = vec![Node::default()];
+            for (word, value) in vocabulary.words() {
+                let mut current = 0;
+                for byte in word.bytes() {
+                    current = match nodes[current].children.iter().find(|(b, _)| b == &byte) {
+                        Some((_, next)) => *next,
+                        None => {
+                            nodes.push(Node::default());
+                            let next = nodes.len() - 1;
+                            nodes[current].children.push((byte, next));
+                            next
+                        }
+                    };
+                }
+                nodes[current].value = Some(*value);
+            }
+            DigitMatcher { nodes }
+        }
+
+        /// The longest word starting exactly at `start`.
+        fn match_at(&self, line: &[u8], start: usize) -> Option<Match> {
+            let mut current = 0;
+            let mut longest = None;
+            for (idx, byte) in line[start..].iter().enumerate() {
+                match self.nodes[current].children.iter().find(|(b, _)| b == byte) {
+                    Some((_, next)) => current = *next,
+                    None => break
+                }
+                if let Some(value) = self.nodes[current].value {
+                    longest = Some(Match { value, start, end: start + idx + 1 });
+                }
+            }
+            longest
+        }
+
+        pub fn first(&self, line: &str) -> Option<Match> {
+            let bytes = line.as_bytes();
+            (0..bytes.len()).find_map(|start| self.match_at(bytes, start))
+        }
+
+        pub fn last(&self, line: &str) -> Option<Match> {
+            let bytes = line.as_bytes();
+            (0..bytes.len()).rev().find_map(|start| self.match_at(bytes, start))
+        }
+    }
+}
+
+mod diagnose {
+    use advent_of_code_2023::common::read_valid_lines;
+    use anyhow::{bail, Result};
+    use crate::calibration_value_words;
+    use crate::matcher::{DigitMatcher, Match};
+    use crate::vocabulary::Vocabulary;
+
+    /// What we found on a single line of the calibration document.
+    #[derive(Debug, PartialEq)]
+    pub struct LineDiagnosis {
+        /// Starting at 1
+        pub number: usize,
+        pub line: String,
+        pub first: Option<Match>,
+        pub last: Option<Match>,
+        /// Only missing if there is no match at all
+        pub value: Option<u32>,
+    }
+
+    #[derive(Debug)]
+    pub struct Diagnostics {
+        pub lines: Vec<LineDiagnosis>,
+    }
+
+    impl Diagnostics {
+        pub fn new(filename: &str, vocabulary: &Vocabulary) -> Self {
+            let matcher = DigitMatcher::new(vocabulary);
+            let lines = read_valid_lines(filename)
+                .enumerate()
+                .map(|(idx, line)| LineDiagnosis {
+                    number: idx + 1,
+                    first: matcher.first(&line),
+                    last: matcher.last(&line),
+                    value: calibration_value_words(&line, &matcher).ok(),
+                    line,
+                })
+                .collect();
+            Diagnostics { lines }
+        }
+
+        pub fn failed(&self) -> impl Iterator<Item=&LineDiagnosis> {
+            self.lines.iter().filter(|line| line.value.is_none())
+        }
+
+        /// The sum of all calibration values. Fails naming all bad lines, unless these are skipped,
+        /// then the sum is only a partial one.
+        pub fn sum(&self, skip_invalid: bool) -> Result<u32> {
+            let failed: Vec<String> = self.failed().map(|line| line.number.to_string()).collect();
+            if !failed.is_empty() && !skip_invalid {
+                bail!("no digit found on line(s) {}", failed.join(", "));
+            }
+            Ok(self.lines.iter().filter_map(|line| line.value).sum())
+        }
+
+        pub fn log(&self) {
+            let position = |m: &Option<Match>| match m {
+                Some(m) => format!("{} at {}..{}", m.value, m.start, m.end),
+                None => "-".to_string()
+            };
+            for line in self.lines.iter() {
+                match line.value {
+                    Some(value) => info!("line {}: '{}', first {}, last {}, value {}", line.number,
+                        line.line, position(&line.first), position(&line.last), value),
+                    None => error!("line {}: '{}', no digit found", line.number, line.line)
+                }
+            }
+            let failed: Vec<String> = self.failed().map(|line| line.number.to_string()).collect();
+            if !failed.is_empty() {
+                error!("{} of {} lines failed: {}", failed.len(), self.lines.len(), failed.join(", "));
+            }
+        }
+    }
+}
+
+mod bench {
+    use std::time::Instant;
+    use advent_of_code_2023::random::Rng;
+    use anyhow::Result;
+    use regex::Regex;
+    use crate::calibration_value_words;
+    use crate::matcher::DigitMatcher;
+    use crate::vocabulary::Vocabulary;
+
+    const WORDS: [&str; 9] = ["one", "two", "three", "four", "five", "six", "seven", "eight", "nine"];
+
+    /// Random lines of letters, digits and number words that all contain at least one digit.
+    pub fn generate_lines(count: usize, seed: u64) -> Vec<String> {
+        let mut rng = Rng::new(seed);
+        (0..count)
+            .map(|_| {
+                let mut line = String::new();
+                for _ in 0..(1 + rng.next(8)) {
+                    match rng.next(3) {
+                        0 => line.push_str(WORDS[rng.next(WORDS.len())]),
+                        1 => line.push((b'1' + rng.next(9) as u8) as char),
+                        _ => line.push((b'a' + rng.next(26) as u8) as char),
+                    }
+                }
+                line.push((b'1' + rng.next(9) as u8) as char);
+                line
+            })
+            .collect()
+    }
+
+    /// The way we solved it first: one regex for the first match, and one that uses a greedy
+    /// `.*` to find the last, possibly overlapping match.
+    pub struct RegexMatcher {
+        first: Regex,
+        last: Regex,
+    }
+
+    impl RegexMatcher {
+        pub fn new() -> Self {
+            let base_regex = r"(1|2|3|4|5|6|7|8|9|one|two|three|four|five|six|seven|eight|nine)";
+            RegexMatcher {
+                first: Regex::new(base_regex).expect("valid regex"),
+                last: Regex::new(format!(".*{}", base_regex).as_str()).expect("valid regex"),
+            }
+        }
+
+        pub fn calibration_value(&self, line: &str) -> Option<u32> {
+            let translator = |num_str: &str| match num_str.parse() {
+                Ok(digit) => digit,
+                Err(_) => WORDS.iter().position(|w| w == &num_str).expect("a number word") as u32 + 1
+            };
+
+            let first = self.first.captures(line)
+                .and_then(|caps| caps.get(1))
+                .map(|mat| translator(&line[mat.range()]))?;
+            let last = self.last.captures(line)
+                .and_then(|caps| caps.get(1))
+                .map(|mat| translator(&line[mat.range()]))
+                .unwrap_or(first);
+
+            Some((first * 10) + last)
+        }
+    }
+
+    pub fn run(count: usize) -> Result<()> {
+        let lines = generate_lines(count, 42);
+
+        let start = Instant::now();
+        let regex = RegexMatcher::new();
+        let regex_sum: u32 = lines.iter().filter_map(|line| regex.calibration_value(line)).sum();
+        let regex_time = start.elapsed();
+
+        let start = Instant::now();
+        let matcher = DigitMatcher::new(&Vocabulary::english());
+        let mut matcher_sum = 0;
+        for line in lines.iter() {
+            matcher_sum += calibration_value_words(line, &matcher)?;
+        }
+        let matcher_time = start.elapsed();
+
+        info!("{} lines, regex: {} in {:?}, matcher: {} in {:?}, {:.1}x faster", count, regex_sum,
+            regex_time, matcher_sum, matcher_time, regex_time.as_secs_f64() / matcher_time.as_secs_f64());
+        Ok(())
+    }
+}
+
+/// Numbers of a vocabulary can have more than one digit, e.g. `twelve`, then the calibration value
+/// is made of the leading digit of the first number and the trailing digit of the last one.
+fn calibration_value_words(line: &str, matcher: &DigitMatcher) -> Result<u32> {
+    let first = matcher.first(line).context("not even one digit found")?;
+    let last = matcher.last(line).expect("at least the first match");
+
+    Ok((leading_digit(first.value) * 10) + last.value % 10)
+}
+
+fn leading_digit(mut value: u32) -> u32 {
+    while value >= 10 {
+        value /= 10;
+    }
+    value
+}
+
+/// Sums the calibration values using any vocabulary.
+fn solve_with(filename: &str, vocabulary: &Vocabulary) -> Result<u32> {
+    let matcher = DigitMatcher::new(vocabulary);
+    let mut sum: u32 = 0;
+    for (idx, line) in read_valid_lines(filename).enumerate() {
+        sum += calibration_value_words(&line, &matcher).with_context(|| format!("line {}", idx + 1))?
+    }
+    Ok(sum)
 }
 
 fn calibration_value(line: String) -> Result<u32> {
 
-    let mut digits = line.chars().filter(|c| c.is_digit(10));
+    let mut digits = line.chars().filter(|c| c.is_ascii_digit());
     let first = digits.next()
         .context("no first digit")?
         .to_digit(10).unwrap();
-    let last = digits.last()
+    let last = digits.next_back()
         .map(|c| c.to_digit(10).unwrap())
         .unwrap_or(first);
 
@@ -67,8 +399,8 @@ fn calibration_value(line: String) -> Result<u32> {
 fn solve_01(filename: &str) -> Result<u32> {
     let lines = read_valid_lines(filename);
     let mut sum: u32 = 0;
-    for line in lines {
-        sum += calibration_value(line)?
+    for (idx, line) in lines.enumerate() {
+        sum += calibration_value(line).with_context(|| format!("line {}", idx + 1))?
     }
     Ok(sum)
 }
@@ -77,22 +409,53 @@ fn solve_01(filename: &str) -> Result<u32> {
 /// with letters: one, two, three, four, five, six, seven, eight, and nine also count as valid
 /// "digits".
 fn solve_02(filename: &str) -> Result<u32> {
-    let lines = read_valid_lines(filename);
-    let mut sum: u32 = 0;
-    for line in lines {
-        sum += calibration_value_words(line)?
-    }
-    Ok(sum)
+    solve_with(filename, &Vocabulary::english())
 }
 
-fn main() {
+fn main() -> Result<()> {
     simple_log::quick!("info");
 
-    let result_1 = solve_01("src/day_01/input.txt");
-    let result_2 = solve_02("src/day_01/input.txt");
+    let args: Vec<String> = env::args().collect();
+    if let Some("bench") = args.get(1).map(String::as_str) {
+        let count = args.get(2).map(|c| c.parse().context("line count")).unwrap_or(Ok(1_000_000))?;
+        bench::run(count)?;
+        return Ok(())
+    }
+    if let Some("diagnose") = args.get(1).map(String::as_str) {
+        let skip_invalid = args.iter().any(|arg| arg == "--skip-invalid");
+        let vocabulary = if args.iter().any(|arg| arg == "--part-1") {
+            Vocabulary::digits().with_zero("0")
+        } else {
+            Vocabulary::english()
+        };
+        let filename = args.iter().skip(2)
+            .find(|arg| !arg.starts_with("--"))
+            .map(String::as_str)
+            .unwrap_or("src/day_01/input.txt");
 
-    info!("Result part 1: {}", result_1.expect("result 1"));
-    info!("Result part 2: {}", result_2.expect("result 2"));
+        let diagnostics = Diagnostics::new(filename, &vocabulary);
+        diagnostics.log();
+        let sum = diagnostics.sum(skip_invalid)
+            .map_err(|error| anyhow!("{:#}, use --skip-invalid for a partial sum", error))?;
+        if skip_invalid {
+            info!("Partial sum: {}", sum);
+        } else {
+            info!("Sum: {}", sum);
+        }
+        return Ok(())
+    }
+    if let Some("vocabulary") = args.get(1).map(String::as_str) {
+        let name = args.get(2).context("usage: day_01 vocabulary <name|file> [input]")?;
+        let filename = args.get(3).map(String::as_str).unwrap_or("src/day_01/input.txt");
+        let vocabulary = Vocabulary::by_name(name)
+            .or_else(|_| Vocabulary::from_file(name))?;
+        info!("Result with {}: {}", name, solve_with(filename, &vocabulary)?);
+        return Ok(())
+    }
+
+    info!("Result part 1: {}", solve_01("src/day_01/input.txt")?);
+    info!("Result part 2: {}", solve_02("src/day_01/input.txt")?);
+    Ok(())
 }
 
 
@@ -100,7 +463,12 @@ fn main() {
 mod tests {
     use crate::solve_01;
     use crate::solve_02;
-    use crate::calibration_value_words;
+    use crate::{bench, calibration_value_words};
+    use crate::bench::RegexMatcher;
+    use crate::matcher::{DigitMatcher, Match};
+    use crate::solve_with;
+    use crate::diagnose::Diagnostics;
+    use crate::vocabulary::Vocabulary;
 
     #[test]
     fn solve_test_input_part_01() {
@@ -116,13 +484,97 @@ mod tests {
 
     #[test]
     fn test_calibration_value_words() {
-        assert_eq!(calibration_value_words("two".to_string()).unwrap(), 22);
-        assert_eq!(calibration_value_words("2".to_string()).unwrap(), 22);
-        assert_eq!(calibration_value_words("21".to_string()).unwrap(), 21);
-        assert_eq!(calibration_value_words("twone45sevenine".to_string()).unwrap(), 29);
-        assert_eq!(calibration_value_words("one23".to_string()).unwrap(), 13);
-        assert_eq!(calibration_value_words("onetwothree".to_string()).unwrap(), 13);
-        assert_eq!(calibration_value_words("eighthree".to_string()).unwrap(), 83);
-        assert_eq!(calibration_value_words("sevenine".to_string()).unwrap(), 79);
-    }
-}
\ No newline at end of file
+        let matcher = DigitMatcher::new(&Vocabulary::english());
+        assert_eq!(calibration_value_words("two", &matcher).unwrap(), 22);
+        assert_eq!(calibration_value_words("2", &matcher).unwrap(), 22);
+        assert_eq!(calibration_value_words("21", &matcher).unwrap(), 21);
+        assert_eq!(calibration_value_words("twone45sevenine", &matcher).unwrap(), 29);
+        assert_eq!(calibration_value_words("one23", &matcher).unwrap(), 13);
+        assert_eq!(calibration_value_words("onetwothree", &matcher).unwrap(), 13);
+        assert_eq!(calibration_value_words("eighthree", &matcher).unwrap(), 83);
+        assert_eq!(calibration_value_words("sevenine", &matcher).unwrap(), 79);
+        assert!(calibration_value_words("abc", &matcher).is_err());
+    }
+
+    #[test]
+    fn test_match_positions() {
+        let matcher = DigitMatcher::new(&Vocabulary::english());
+        assert_eq!(matcher.first("xtwone3"), Some(Match { value: 2, start: 1, end: 4 }));
+        assert_eq!(matcher.last("xtwone"), Some(Match { value: 1, start: 3, end: 6 }));
+        assert_eq!(matcher.last("oneight"), Some(Match { value: 8, start: 2, end: 7 }));
+        assert_eq!(matcher.first("abc"), None);
+    }
+
+    #[test]
+    fn matcher_agrees_with_regex() {
+        let matcher = DigitMatcher::new(&Vocabulary::english());
+        let regex = RegexMatcher::new();
+        for line in bench::generate_lines(2_000, 7) {
+            assert_eq!(calibration_value_words(&line, &matcher).ok(), regex.calibration_value(&line), "{}", line);
+        }
+    }
+
+    #[test]
+    fn other_languages() {
+        let german = DigitMatcher::new(&Vocabulary::german());
+        assert_eq!(calibration_value_words("zweinsiebenacht", &german).unwrap(), 28);
+        assert_eq!(calibration_value_words("xfünfzig", &german).unwrap(), 55);
+        assert_eq!(calibration_value_words("achtzehneun", &german).unwrap(), 89);
+
+        let french = DigitMatcher::new(&Vocabulary::french());
+        assert_eq!(calibration_value_words("troisixhuit", &french).unwrap(), 38);
+        assert_eq!(calibration_value_words("neufunx", &french).unwrap(), 91);
+    }
+
+    #[test]
+    fn zero_and_multi_digit_words() {
+        let matcher = DigitMatcher::new(&Vocabulary::english().with_zero("zero"));
+        assert_eq!(calibration_value_words("zeroone", &matcher).unwrap(), 1);
+        assert_eq!(calibration_value_words("a0b", &matcher).unwrap(), 0);
+
+        let matcher = DigitMatcher::new(&Vocabulary::english_to_twenty());
+        assert_eq!(calibration_value_words("twelve", &matcher).unwrap(), 12);
+        assert_eq!(calibration_value_words("seventeenx3", &matcher).unwrap(), 13);
+        assert_eq!(calibration_value_words("4eighteen", &matcher).unwrap(), 48);
+        assert_eq!(calibration_value_words("twentwelve", &matcher).unwrap(), 12);
+        assert_eq!(calibration_value_words("nineteenine", &matcher).unwrap(), 19);
+    }
+
+    #[test]
+    fn vocabulary_from_file() {
+        let spanish = Vocabulary::from_file("src/day_01/vocabulary_spanish.txt").unwrap();
+        assert_eq!(solve_with("src/day_01/test_input_spanish.txt", &spanish).unwrap(), 179);
+
+        assert_eq!(Vocabulary::by_name("german+zero").unwrap(), Vocabulary::german().with_zero("null"));
+        assert!(Vocabulary::by_name("klingon").is_err());
+        assert!(Vocabulary::english().with_word("one", 2).is_err());
+        assert!(Vocabulary::english().with_word("one", 1).is_ok());
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let error = solve_01("src/day_01/test_input_invalid.txt").unwrap_err();
+        assert_eq!(format!("{:#}", error), "line 2: no first digit");
+        let error = solve_02("src/day_01/test_input_invalid.txt").unwrap_err();
+        assert_eq!(format!("{:#}", error), "line 4: not even one digit found");
+    }
+
+    #[test]
+    fn diagnose_lines() {
+        let diagnostics = Diagnostics::new("src/day_01/test_input_invalid.txt", &Vocabulary::english());
+
+        let failed: Vec<usize> = diagnostics.failed().map(|line| line.number).collect();
+        assert_eq!(failed, vec![4]);
+        assert_eq!(diagnostics.lines[1].first, Some(Match { value: 2, start: 1, end: 4 }));
+        assert_eq!(diagnostics.lines[1].last, Some(Match { value: 1, start: 3, end: 6 }));
+        assert_eq!(diagnostics.lines[1].value, Some(21));
+
+        assert_eq!(diagnostics.sum(false).unwrap_err().to_string(), "no digit found on line(s) 4");
+        assert_eq!(diagnostics.sum(true).unwrap(), 12 + 21 + 77);
+
+        let digits_only = Diagnostics::new("src/day_01/test_input_invalid.txt", &Vocabulary::digits());
+        let failed: Vec<usize> = digits_only.failed().map(|line| line.number).collect();
+        assert_eq!(failed, vec![2, 4]);
+        assert_eq!(digits_only.sum(true).unwrap(), 12 + 77);
+    }
+}
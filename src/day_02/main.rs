@@ -1,109 +1,296 @@
-use std::cmp::{max};
-use advent_of_code_2023::common::read_valid_lines;
+use std::env;
+use std::path::Path;
 use anyhow::{Result, Context};
-use aoc_parse::{parser, prelude::*};
+use crate::bag::{minimal_bag, possible_games, Bag};
+use crate::likelihood::{game_log_likelihood, maximum_likelihood_bag};
+use crate::parse::parse_input;
 
-#[derive(Debug)]
-enum Color {
-    Blue,
-    Red,
-    Green
-}
+mod parse {
+    use std::collections::HashMap;
+    use std::fs::read_to_string;
+    use aoc_parse::{parser, prelude::*};
+    use anyhow::{bail, Context, Result};
 
-#[derive(Debug)]
-struct DrawItem {
-    color: Color,
-    amount: u32
-}
+    #[derive(Debug)]
+    pub struct DrawItem {
+        pub color: String,
+        pub amount: u32
+    }
 
-#[derive(Debug)]
-struct Draw {
-    items: Vec<DrawItem>
-}
+    #[derive(Debug)]
+    pub struct Draw {
+        pub items: Vec<DrawItem>
+    }
 
-#[derive(Debug)]
-struct Game {
-    id: u32,
-    draws: Vec<Draw>
-}
+    #[derive(Debug)]
+    pub struct Game {
+        pub id: u32,
+        pub draws: Vec<Draw>
+    }
 
-fn parse_line(line: String) -> Result<Game> {
-    let game_p = parser!("Game " u32 ": ");
-    let color_p = parser!({
-        "red" => Color::Red,
-        "green" => Color::Green,
-        "blue" => Color::Blue
-    });
-    let draw_item_p = parser!(amount: u32 " " color:color_p => DrawItem { color, amount });
-    let draw_p = parser!(items:repeat_sep(draw_item_p, ", ") => Draw { items });
-    let line_p = parser!(id:game_p draws:repeat_sep(draw_p, "; ") => Game { id, draws });
-    line_p.parse(&line).context("parsing failed")
-}
+    pub fn parse_input(filename: &str) -> Result<Vec<Game>> {
+        let raw_data = read_to_string(filename)
+            .with_context(|| format!("can't read {}", filename))?;
+        parse_games(&raw_data)
+    }
 
-fn is_possible_item(draw_item: &DrawItem) -> bool {
-    match draw_item {
-        DrawItem{ color: Color::Red, amount} => amount <= &12,
-        DrawItem{ color: Color::Green, amount} => amount <= &13,
-        DrawItem{ color: Color::Blue, amount} => amount <= &14
+    /// Parses one game per line. Games have to be numbered 1, 2, 3, ... in order, so a missing,
+    /// repeated, or shuffled game is reported instead of quietly changing the sum of IDs.
+    pub fn parse_games(raw_data: &str) -> Result<Vec<Game>> {
+        let game_p = parser!("Game " u32 ": ");
+        let draw_item_p = parser!(amount: u32 " " color:string(alpha+) => DrawItem { color, amount });
+        let draw_p = parser!(items:repeat_sep(draw_item_p, ", ") => Draw { items });
+        let line_p = parser!(id:game_p draws:repeat_sep(draw_p, "; ") => Game { id, draws });
+
+        let mut first_seen: HashMap<u32, usize> = HashMap::new();
+        let mut games = Vec::new();
+        for (idx, line) in raw_data.lines().enumerate() {
+            let game = line_p.parse(line)
+                .with_context(|| format!("line {}: can't parse '{}'", idx + 1, line))?;
+            if let Some(first_line) = first_seen.insert(game.id, idx + 1) {
+                bail!("line {}: game {} was already on line {}", idx + 1, game.id, first_line);
+            }
+            if game.id as usize != idx + 1 {
+                bail!("line {}: expected game {}, found game {}", idx + 1, idx + 1, game.id);
+            }
+            games.push(game);
+        }
+        Ok(games)
     }
 }
 
-fn is_possible(game: &Game) -> bool {
-    for draw in &game.draws {
-        for draw_item in &draw.items {
-            if !is_possible_item(draw_item) {
-                return false
+mod bag {
+    use std::collections::BTreeMap;
+    use std::fmt;
+    use std::fmt::Formatter;
+    use std::fs::read_to_string;
+    use std::str::FromStr;
+    use anyhow::{ensure, Context, Error, Result};
+    use crate::parse::{Draw, Game};
+
+    /// The cubes in the bag by colour. Colours that aren't in the bag can't be drawn at all.
+    #[derive(Debug, Clone, PartialEq, Default)]
+    pub struct Bag {
+        pub cubes: BTreeMap<String, u32>,
+    }
+
+    impl Bag {
+        /// The bag of the puzzle: 12 red cubes, 13 green cubes, and 14 blue cubes.
+        pub fn puzzle() -> Self {
+            "12 red, 13 green, 14 blue".parse().expect("valid bag")
+        }
+
+        /// Reads a bag written like `12 red, 13 green`, colours may also be on lines of their own.
+        /// Lines starting with `#` are ignored.
+        pub fn from_file(filename: &str) -> Result<Self> {
+            read_to_string(filename)
+                .with_context(|| format!("can't read bag {}", filename))?
+                .lines()
+                .filter(|line| !line.trim_start().starts_with('#'))
+                .collect::<Vec<&str>>()
+                .join("\n")
+                .parse()
+        }
+
+        pub fn count(&self, color: &str) -> u32 {
+            self.cubes.get(color).copied().unwrap_or(0)
+        }
+
+        pub fn contains(&self, draw: &Draw) -> bool {
+            draw.items.iter().all(|item| item.amount <= self.count(&item.color))
+        }
+
+        pub fn is_possible(&self, game: &Game) -> bool {
+            game.draws.iter().all(|draw| self.contains(draw))
+        }
+
+        /// The number of cubes of all colours multiplied together.
+        pub fn power(&self) -> u64 {
+            self.cubes.values().map(|amount| *amount as u64).product()
+        }
+    }
+
+    impl FromStr for Bag {
+        type Err = Error;
+
+        fn from_str(s: &str) -> Result<Self> {
+            let mut cubes = BTreeMap::new();
+            for entry in s.split([',', '\n']).map(str::trim).filter(|entry| !entry.is_empty()) {
+                let (amount, color) = entry.split_once(' ')
+                    .with_context(|| format!("expected '<amount> <colour>', got '{}'", entry))?;
+                let amount = amount.parse()
+                    .with_context(|| format!("'{}' is not an amount", amount))?;
+                let color = color.trim();
+                ensure!(cubes.insert(color.to_string(), amount).is_none(), "{} is in the bag twice", color);
             }
+            Ok(Bag { cubes })
         }
     }
-    true
-}
 
-fn solve_part_1(filename: &str) -> Result<u32> {
-    let lines = read_valid_lines(filename);
-    let mut id_sum = 0;
-    for line in lines {
-        let game = parse_line(line)?;
-        if is_possible(&game) {
-            id_sum += game.id
+    impl fmt::Display for Bag {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            let entries: Vec<String> = self.cubes.iter()
+                .map(|(color, amount)| format!("{} {}", amount, color))
+                .collect();
+            write!(f, "{}", entries.join(", "))
         }
     }
 
-    Ok(id_sum)
+    /// All games that could have been played with the given bag.
+    pub fn possible_games<'a>(games: &'a [Game], bag: &Bag) -> Vec<&'a Game> {
+        games.iter().filter(|game| bag.is_possible(game)).collect()
+    }
+
+    /// The fewest cubes of each colour the bag must have held for the game to be possible. All
+    /// colours of `colors` are part of the result, even if the game never showed them.
+    pub fn minimal_bag(game: &Game, colors: &Bag) -> Bag {
+        let mut cubes: BTreeMap<String, u32> = colors.cubes.keys()
+            .map(|color| (color.clone(), 0))
+            .collect();
+        for item in game.draws.iter().flat_map(|draw| draw.items.iter()) {
+            let amount = cubes.entry(item.color.clone()).or_default();
+            *amount = (*amount).max(item.amount);
+        }
+        Bag { cubes }
+    }
 }
 
-fn power_of_draws(draws: Vec<Draw>) -> u32 {
-    let mut max_red = 0;
-    let mut max_blue = 0;
-    let mut max_green = 0;
-
-    for draw in draws {
-        for item in draw.items {
-            match item.color {
-                Color::Red => max_red = max(max_red, item.amount),
-                Color::Blue => max_blue = max(max_blue, item.amount),
-                Color::Green => max_green = max(max_green, item.amount)
+mod likelihood {
+    use std::collections::BTreeMap;
+    use crate::bag::{minimal_bag, Bag};
+    use crate::parse::{Draw, Game};
+
+    /// `ln(n choose k)`, summed up step by step as bags only hold a handful of cubes.
+    fn ln_binomial(n: u32, k: u32) -> f64 {
+        (0..k).map(|i| ((n - i) as f64).ln() - ((i + 1) as f64).ln()).sum()
+    }
+
+    /// The natural log of the probability to draw exactly these cubes at once, i.e. without
+    /// replacement, which is `C(n_1, k_1) * ... * C(n_m, k_m) / C(N, K)` with `n` being the cubes
+    /// of each colour in the bag and `k` the drawn ones. Impossible draws give negative infinity.
+    pub fn draw_log_likelihood(bag: &Bag, draw: &Draw) -> f64 {
+        let mut drawn: BTreeMap<&str, u32> = BTreeMap::new();
+        for item in draw.items.iter() {
+            *drawn.entry(&item.color).or_default() += item.amount;
+        }
+        if drawn.iter().any(|(color, amount)| *amount > bag.count(color)) {
+            return f64::NEG_INFINITY;
+        }
+
+        let total_in_bag = bag.cubes.values().sum();
+        let total_drawn = drawn.values().sum();
+        drawn.iter()
+            .map(|(color, amount)| ln_binomial(bag.count(color), *amount))
+            .sum::<f64>() - ln_binomial(total_in_bag, total_drawn)
+    }
+
+    /// The cubes are put back between draws, so the draws of a game are independent.
+    pub fn game_log_likelihood(bag: &Bag, game: &Game) -> f64 {
+        game.draws.iter().map(|draw| draw_log_likelihood(bag, draw)).sum()
+    }
+
+    /// The bag with at most `max_cubes` cubes that makes the game most likely, and its log
+    /// likelihood. Without such a limit there often is no best bag, as more cubes in the right
+    /// proportions keep making the game a little more likely. Only colours seen in the game are
+    /// considered, any other cube just lowers the likelihood. Of equally likely bags the one with
+    /// the fewest cubes wins. `None` if not even the minimal bag fits.
+    pub fn maximum_likelihood_bag(game: &Game, max_cubes: u32) -> Option<(Bag, f64)> {
+        let minimal = minimal_bag(game, &Bag::default());
+        let minimal_cubes: u32 = minimal.cubes.values().sum();
+        let spare = max_cubes.checked_sub(minimal_cubes)?;
+
+        let colors: Vec<String> = minimal.cubes.keys().cloned().collect();
+        let mut best = (minimal.clone(), game_log_likelihood(&minimal, game));
+        for extra in 1..=spare {
+            distribute(&minimal, &colors, extra, &mut |bag| {
+                let log_likelihood = game_log_likelihood(bag, game);
+                // ignore rounding noise, so equally likely bags really are equal
+                if log_likelihood > best.1 + 1e-12 {
+                    best = (bag.clone(), log_likelihood);
+                }
+            });
+        }
+        Some(best)
+    }
+
+    /// Calls `visit` for every way to add `extra` cubes of the given colours to the bag.
+    fn distribute<F: FnMut(&Bag)>(bag: &Bag, colors: &[String], extra: u32, visit: &mut F) {
+        let Some((color, others)) = colors.split_first() else {
+            if extra == 0 {
+                visit(bag);
             }
+            return;
+        };
+        let range = if others.is_empty() { extra..=extra } else { 0..=extra };
+        for added in range {
+            let mut next = bag.clone();
+            *next.cubes.entry(color.clone()).or_default() += added;
+            distribute(&next, others, extra - added, visit);
         }
     }
+}
 
-    max_red * max_green * max_blue
+fn solve_part_1(filename: &str, bag: &Bag) -> Result<u32> {
+    let games = parse_input(filename)?;
+    Ok(possible_games(&games, bag).iter().map(|game| game.id).sum())
 }
 
-fn solve_part_2(filename: &str) -> Result<u32> {
-    let lines = read_valid_lines(filename);
-    let mut sum_of_powers = 0;
-    for line in lines {
-        let game = parse_line(line)?;
-        let power = power_of_draws(game.draws);
-        sum_of_powers += power
+fn solve_part_2(filename: &str, bag: &Bag) -> Result<u64> {
+    let games = parse_input(filename)?;
+    Ok(games.iter().map(|game| minimal_bag(game, bag).power()).sum())
+}
+
+/// A bag is either given right on the command line or as the name of a file.
+fn bag_argument(arg: Option<&String>) -> Result<Bag> {
+    match arg {
+        Some(arg) if Path::new(arg).is_file() => Bag::from_file(arg),
+        Some(arg) => arg.parse(),
+        None => Ok(Bag::puzzle())
     }
-    Ok(sum_of_powers)
 }
 
 fn main() -> Result<()> {
-    println!("Result part 1: {}", solve_part_1("src/day_02/input.txt")?);
-    println!("Result part 2: {}", solve_part_2("src/day_02/input.txt")?);
+    let args: Vec<String> = env::args().collect();
+    if let Some("possible") = args.get(1).map(String::as_str) {
+        let bag = bag_argument(args.get(2))?;
+        let filename = args.get(3).map(String::as_str).unwrap_or("src/day_02/input.txt");
+        let games = parse_input(filename)?;
+        let possible = possible_games(&games, &bag);
+        let ids: Vec<String> = possible.iter().map(|game| game.id.to_string()).collect();
+        println!("Possible with {}: {}", bag, ids.join(", "));
+        println!("Sum of IDs: {}", possible.iter().map(|game| game.id).sum::<u32>());
+        return Ok(())
+    }
+    if let Some("minimal") = args.get(1).map(String::as_str) {
+        let filename = args.get(2).map(String::as_str).unwrap_or("src/day_02/input.txt");
+        for game in parse_input(filename)? {
+            let bag = minimal_bag(&game, &Bag::puzzle());
+            println!("Game {}: {} (power {})", game.id, bag, bag.power());
+        }
+        return Ok(())
+    }
+    if let Some("likelihood") = args.get(1).map(String::as_str) {
+        let bag = bag_argument(args.get(2))?;
+        let max_cubes = match args.get(3) {
+            Some(max_cubes) => max_cubes.parse().context("maximum number of cubes")?,
+            None => bag.cubes.values().sum()
+        };
+        let filename = args.get(4).map(String::as_str).unwrap_or("src/day_02/input.txt");
+        for game in parse_input(filename)? {
+            let likelihood = game_log_likelihood(&bag, &game).exp();
+            match maximum_likelihood_bag(&game, max_cubes) {
+                Some((best, log_likelihood)) => println!("Game {}: {:e} with {}, at most {:e} with {}",
+                    game.id, likelihood, bag, log_likelihood.exp(), best),
+                None => println!("Game {}: {:e} with {}, needs more than {} cubes",
+                    game.id, likelihood, bag, max_cubes)
+            }
+        }
+        return Ok(())
+    }
+
+    let bag = Bag::puzzle();
+    println!("Result part 1: {}", solve_part_1("src/day_02/input.txt", &bag)?);
+    println!("Result part 2: {}", solve_part_2("src/day_02/input.txt", &bag)?);
     Ok(())
 }
 
@@ -111,16 +298,103 @@ fn main() -> Result<()> {
 #[cfg(test)]
 mod tests {
     use crate::{solve_part_1, solve_part_2};
+    use crate::bag::{minimal_bag, possible_games, Bag};
+    use crate::likelihood::{draw_log_likelihood, game_log_likelihood, maximum_likelihood_bag};
+    use crate::parse::{parse_games, parse_input, Game};
+
+    fn single_game(line: &str) -> Game {
+        parse_games(line).unwrap().remove(0)
+    }
 
     #[test]
     fn solve_test_input_1() {
-        let result = solve_part_1("src/day_02/test_input.txt").unwrap();
+        let result = solve_part_1("src/day_02/test_input.txt", &Bag::puzzle()).unwrap();
         assert_eq!(result, 8);
     }
 
     #[test]
     fn solve_test_input_2() {
-        let result = solve_part_2("src/day_02/test_input.txt").unwrap();
+        let result = solve_part_2("src/day_02/test_input.txt", &Bag::puzzle()).unwrap();
         assert_eq!(result, 2286);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn parse_bags() {
+        let bag: Bag = "12 red, 13 green\n14 blue".parse().unwrap();
+        assert_eq!(bag, Bag::puzzle());
+        assert_eq!(bag.to_string(), "14 blue, 13 green, 12 red");
+        assert_eq!(Bag::from_file("src/day_02/bag_rainbow.txt").unwrap().count("violet"), 2);
+
+        assert!("12 red, 1 red".parse::<Bag>().is_err());
+        assert!("red".parse::<Bag>().is_err());
+        assert!("many red".parse::<Bag>().is_err());
+    }
+
+    #[test]
+    fn possible_games_for_bag() {
+        let games = parse_input("src/day_02/test_input.txt").unwrap();
+        let ids = |bag: &Bag| possible_games(&games, bag).iter().map(|game| game.id).collect::<Vec<u32>>();
+
+        assert_eq!(ids(&Bag::puzzle()), vec![1, 2, 5]);
+        assert_eq!(ids(&"20 red, 13 green, 15 blue".parse().unwrap()), vec![1, 2, 3, 4, 5]);
+        assert_eq!(ids(&"20 red, 13 green".parse().unwrap()), vec![]);
+    }
+
+    #[test]
+    fn minimal_bags() {
+        let games = parse_input("src/day_02/test_input_rainbow.txt").unwrap();
+
+        let bag = minimal_bag(&games[0], &Bag::puzzle());
+        assert_eq!(bag.to_string(), "0 blue, 3 green, 4 red, 2 violet");
+        assert_eq!(bag.power(), 0);
+
+        let bag = minimal_bag(&games[1], &Bag::default());
+        assert_eq!(bag.to_string(), "5 green, 1 orange, 2 violet");
+        assert_eq!(bag.power(), 10);
+
+        let rainbow = Bag::from_file("src/day_02/bag_rainbow.txt").unwrap();
+        assert_eq!(possible_games(&games, &rainbow).len(), 2);
+        assert_eq!(possible_games(&games, &Bag::puzzle()).len(), 0);
+    }
+
+    #[test]
+    fn likelihood_of_draws() {
+        let game = single_game("Game 1: 2 red; 1 red, 1 blue");
+        let bag: Bag = "2 red, 2 blue".parse().unwrap();
+
+        assert!((draw_log_likelihood(&bag, &game.draws[0]).exp() - 1.0 / 6.0).abs() < 1e-12);
+        assert!((draw_log_likelihood(&bag, &game.draws[1]).exp() - 4.0 / 6.0).abs() < 1e-12);
+        assert!((game_log_likelihood(&bag, &game).exp() - 4.0 / 36.0).abs() < 1e-12);
+
+        let too_small: Bag = "1 red, 2 blue".parse().unwrap();
+        assert_eq!(game_log_likelihood(&too_small, &game), f64::NEG_INFINITY);
+        let certain = single_game("Game 1: 3 green");
+        assert_eq!(game_log_likelihood(&"3 green".parse().unwrap(), &certain), 0.0);
+    }
+
+    #[test]
+    fn maximum_likelihood_bags() {
+        let game = single_game("Game 1: 1 red; 1 blue");
+        let (bag, log_likelihood) = maximum_likelihood_bag(&game, 10).unwrap();
+        assert_eq!(bag.to_string(), "1 blue, 1 red");
+        assert!((log_likelihood.exp() - 0.25).abs() < 1e-12);
+
+        let game = single_game("Game 1: 2 red; 1 blue");
+        let (bag, log_likelihood) = maximum_likelihood_bag(&game, 4).unwrap();
+        assert_eq!(bag.to_string(), "1 blue, 3 red");
+        assert!((log_likelihood.exp() - 0.125).abs() < 1e-12);
+        assert!(maximum_likelihood_bag(&game, 2).is_none());
+    }
+
+    #[test]
+    fn invalid_games() {
+        let error = parse_games("Game 1: 3 blue\nGame 2: 3 blue 4 red").unwrap_err();
+        assert_eq!(error.to_string(), "line 2: can't parse 'Game 2: 3 blue 4 red'");
+        let error = parse_games("Game 1: 3 blue\nGame 1: 4 red").unwrap_err();
+        assert_eq!(error.to_string(), "line 2: game 1 was already on line 1");
+        let error = parse_games("Game 1: 3 blue\nGame 3: 4 red").unwrap_err();
+        assert_eq!(error.to_string(), "line 2: expected game 2, found game 3");
+        let error = parse_input("src/day_02/missing.txt").unwrap_err();
+        assert_eq!(error.to_string(), "can't read src/day_02/missing.txt");
+    }
+}
@@ -1,148 +1,659 @@
 extern crate core;
 
-use std::collections::HashMap;
-use std::fs::{read_to_string};
+use std::env;
+use std::fs::{self, read_to_string, File};
+use std::io::BufWriter;
+use std::time::Duration;
+use advent_of_code_2023::common::flag_value;
+use advent_of_code_2023::image::{grid_svg, write_gif, Image};
+use advent_of_code_2023::visualize::{Color, Visualizer};
 use anyhow::{Result, Context};
-use aoc_parse::{parser, prelude::*};
+use crate::rules::{GearRule, Symbols};
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 struct Point {
-    x: usize,
-    y: usize
+    // y first, so points sort row by row
+    y: usize,
+    x: usize
 }
 
-#[derive(Debug)]
-enum Number {
-    Emtpy,
-    PartNumber(String)
+/// A number of the schematic, `position` is where its first digit is.
+#[derive(Debug, PartialEq)]
+struct Number {
+    value: u32,
+    position: Point,
+    len: usize,
 }
 
-#[derive(Debug)]
-enum Part {
-    Emtpy,
-    Gear,
-    OtherPart,
+/// Anything that is neither a digit nor a dot.
+#[derive(Debug, PartialEq)]
+struct Symbol {
+    symbol: char,
+    position: Point,
 }
 
 #[derive(Debug)]
 struct Plan {
-    numbers: Vec<Vec<Number>>,
-    parts: Vec<Vec<Part>>,
+    width: usize,
+    height: usize,
+    numbers: Vec<Number>,
+    /// Row by row
+    symbols: Vec<Symbol>,
+    /// For every cell, row by row, the index of the symbol on it
+    symbol_index: Vec<Option<usize>>,
 }
 
 impl Number {
 
-    /// Produce a vector of all adjacent, surrounding points (so 1 point bounding box)
-    /// relative to the location given. For PartNumber the location expected to be the
-    /// top left coordinate.
-    /// Function makes sure to not produce negative locations but does not know about
+    /// All adjacent, surrounding points (so 1 point bounding box) of the number.
+    /// Makes sure to not produce negative locations but does not know about
     /// max global locations.
-    fn adjacent(self: &Self, base: Point) -> Box<dyn Iterator<Item=Point> + '_> {
-        let safe_base = |n: usize | if n == 0 {
-            0
-        } else {
-            n - 1
-        };
+    fn adjacent(&self) -> impl Iterator<Item=Point> + '_ {
+        let base = self.position;
+        let xs = base.x.saturating_sub(1)..(base.x + self.len + 1);
+        let ys = base.y.saturating_sub(1)..(base.y + 2);
 
-        let xs = safe_base(base.x)..(base.x + self.len() + 1);
-        let ys = safe_base(base.y)..(base.y + 2);
-
-        let all_points = ys
-            .flat_map(move |y| {
-                xs.clone().map(move |x| Point {x, y})
-            })
+        ys.flat_map(move |y| xs.clone().map(move |x| Point {x, y}))
             // filter out points that match the text
-            .filter(move |p| if p.y != base.y {
-                true
-            } else {
-                p.x < base.x || p.x >= (base.x + self.len())
-            });
+            .filter(move |p| p.y != base.y || p.x < base.x || p.x >= base.x + self.len)
+    }
+}
+
+/// A symbol of the schematic together with all numbers around it.
+#[derive(Debug, PartialEq)]
+struct SymbolNeighbours {
+    symbol: char,
+    position: Point,
+    numbers: Vec<u32>,
+}
+
+impl Plan {
+    /// Reads the schematic in a single pass, collecting numbers and symbols as they come along.
+    /// Lines may differ in length, missing cells are empty.
+    fn scan(raw_data: &str) -> Result<Plan> {
+        let lines: Vec<&str> = raw_data.lines().collect();
+        let height = lines.len();
+        let width = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+
+        let mut numbers = vec![];
+        let mut symbols = vec![];
+        let mut symbol_index = vec![None; width * height];
+        for (y, line) in lines.iter().enumerate() {
+            let mut current: Option<Number> = None;
+            for (x, c) in line.chars().enumerate() {
+                if let Some(digit) = c.to_digit(10) {
+                    let number = current.get_or_insert(Number { value: 0, position: Point {x, y}, len: 0 });
+                    number.value = number.value.checked_mul(10)
+                        .and_then(|value| value.checked_add(digit))
+                        .with_context(|| format!("line {}: number at column {} too large", y + 1, number.position.x + 1))?;
+                    number.len += 1;
+                    continue;
+                }
 
-        Box::new(all_points)
+                numbers.extend(current.take());
+                if c != '.' {
+                    symbol_index[y * width + x] = Some(symbols.len());
+                    symbols.push(Symbol { symbol: c, position: Point {x, y} });
+                }
+            }
+            numbers.extend(current.take());
+        }
+
+        Ok(Plan { width, height, numbers, symbols, symbol_index })
+    }
+
+    /// The index of the symbol at `p`, if there is one and it counts.
+    fn symbol_at(&self, p: Point, symbols: &Symbols) -> Option<usize> {
+        if p.x >= self.width || p.y >= self.height {
+            return None;
+        }
+        self.symbol_index[p.y * self.width + p.x]
+            .filter(|idx| symbols.contains(self.symbols[*idx].symbol))
     }
 
-    fn len(self: &Self) -> usize {
-        match self {
-            Number::Emtpy => 1,
-            Number::PartNumber(s) => s.len()
+    /// Every symbol that counts, ordered row by row, with the numbers touching it.
+    fn symbols(&self, symbols: &Symbols) -> Vec<SymbolNeighbours> {
+        let mut neighbours: Vec<Vec<u32>> = vec![vec![]; self.symbols.len()];
+        for number in self.numbers.iter() {
+            for idx in number.adjacent().filter_map(|p| self.symbol_at(p, symbols)) {
+                neighbours[idx].push(number.value);
+            }
         }
+
+        self.symbols.iter()
+            .zip(neighbours)
+            .filter(|(symbol, _)| symbols.contains(symbol.symbol))
+            .map(|(symbol, numbers)| SymbolNeighbours { symbol: symbol.symbol, position: symbol.position, numbers })
+            .collect()
+    }
+
+    fn is_part_number(&self, number: &Number, symbols: &Symbols) -> bool {
+        number.adjacent().any(|p| self.symbol_at(p, symbols).is_some())
+    }
+
+    /// All numbers next to at least one symbol, each counted once even if it touches several.
+    fn part_numbers(&self, symbols: &Symbols) -> Vec<u32> {
+        self.numbers.iter()
+            .filter(|number| self.is_part_number(number, symbols))
+            .map(|number| number.value)
+            .collect()
     }
 }
 
 fn parse_input(filename: &str) -> Result<Plan> {
-    let part_parser = parser!({
-        "." => Part::Emtpy,
-        "*" => Part::Gear,
-        digit => Part::Emtpy,
-        any_char => Part::OtherPart,
-    });
-    let number_parser = parser!({
-        s:string(digit+) => Number::PartNumber(s),
-        any_char => Number::Emtpy
-    });
-
-    let raw_data = read_to_string(filename)?;
-    let parts = parser!(lines(part_parser+))
-        .parse(&raw_data)
-        .context("error parsing parts")?;
-    let numbers = parser!(lines(number_parser+))
-        .parse(&raw_data)
-        .context("error parsing numbers")?;
-
-    Ok(Plan {parts, numbers})
+    let raw_data = read_to_string(filename)
+        .with_context(|| format!("can't read {}", filename))?;
+    Plan::scan(&raw_data)
 }
 
-fn solve(filename: &str) -> Result<(u32, u32)> {
-    let plan = parse_input(filename)?;
+mod bench {
+    use std::time::Instant;
+    use advent_of_code_2023::random::Rng;
+    use anyhow::Result;
+    use crate::Plan;
+    use crate::rules::{GearRule, Symbols};
+
+    const SYMBOLS: [char; 10] = ['*', '#', '+', '$', '/', '=', '%', '@', '&', '-'];
 
-    // we assume a rectangular plan
-    let max_x = plan.parts[0].len() - 1;
-    let max_y = plan.parts.len() - 1;
-
-    let mut total_of_part_numbers = 0;
-    let mut gears: HashMap<Point, Vec<u32>> = HashMap::new();
-
-    for (y, row) in plan.numbers.iter().enumerate() {
-        let mut x = 0;
-        for number in row {
-            if let Number::PartNumber(s) = number {
-                let part_number: u32 = s.parse()?;
-                let adjacent_points = number.adjacent(Point {x, y});
-                let valid_adjacent = adjacent_points
-                    .filter(|p| p.x <= max_x && p.y <= max_y);
-
-                for p in valid_adjacent {
-                    match plan.parts[p.y][p.x] {
-                        Part::Gear => {
-                            total_of_part_numbers += part_number;
-
-                            gears.entry(p.clone())
-                                .and_modify(|v| v.push(part_number))
-                                .or_insert(vec![part_number]);
-                        },
-                        Part::OtherPart => {
-                            total_of_part_numbers += part_number;
+    /// A square schematic with numbers of up to three digits and a sprinkle of symbols.
+    pub fn generate(size: usize, seed: u64) -> String {
+        let mut rng = Rng::new(seed);
+        let mut schematic = String::with_capacity((size + 1) * size);
+        for _ in 0..size {
+            let mut x = 0;
+            while x < size {
+                match rng.next(10) {
+                    0..=1 => {
+                        let len = (1 + rng.next(3)).min(size - x);
+                        for _ in 0..len {
+                            schematic.push((b'0' + rng.next(10) as u8) as char);
                         }
-                        _ => {} // do nothing
+                        x += len;
+                        if x < size {
+                            schematic.push('.');
+                            x += 1;
+                        }
+                    }
+                    2 => {
+                        schematic.push(SYMBOLS[rng.next(SYMBOLS.len())]);
+                        x += 1;
+                    }
+                    _ => {
+                        schematic.push('.');
+                        x += 1;
                     }
                 }
+            }
+            schematic.push('\n');
+        }
+        schematic
+    }
+
+    pub fn run(size: usize) -> Result<()> {
+        let schematic = generate(size, 42);
+
+        let start = Instant::now();
+        let plan = Plan::scan(&schematic)?;
+        let scan_time = start.elapsed();
+
+        let start = Instant::now();
+        let symbols = Symbols::puzzle();
+        let gear_rule = GearRule::puzzle();
+        let part_sum: u64 = plan.part_numbers(&symbols).iter().map(|n| *n as u64).sum();
+        let gear_ratios: u64 = plan.symbols(&symbols).iter()
+            .filter_map(|symbol| gear_rule.ratio(symbol.symbol, &symbol.numbers))
+            .sum();
+        let solve_time = start.elapsed();
 
+        let cells = (size * size) as f64;
+        println!("{}x{} schematic with {} numbers and {} symbols", size, size, plan.numbers.len(), plan.symbols.len());
+        println!("scan: {:?} ({:.0} cells/ms), solve: {:?} ({:.0} cells/ms)", scan_time,
+            cells / scan_time.as_secs_f64() / 1000.0, solve_time, cells / solve_time.as_secs_f64() / 1000.0);
+        println!("part numbers: {}, gear ratios: {}", part_sum, gear_ratios);
+        Ok(())
+    }
+}
+
+mod rules {
+    use std::str::FromStr;
+    use anyhow::{bail, Context, Error, Result};
+
+    /// Which characters of the schematic are symbols. Dots and digits never are.
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Symbols {
+        AllExcept(Vec<char>),
+        Only(Vec<char>),
+    }
+
+    impl Symbols {
+        /// Everything but dots and digits, as in the puzzle.
+        pub fn puzzle() -> Self {
+            Symbols::AllExcept(vec![])
+        }
+
+        pub fn contains(&self, c: char) -> bool {
+            match self {
+                Symbols::AllExcept(excluded) => !excluded.contains(&c),
+                Symbols::Only(included) => included.contains(&c),
             }
-            x += number.len()
         }
     }
 
-    let gear_rations = gears.values()
-        .filter(|parts| parts.len() == 2)
-        .map(|parts| parts[0] * parts[1])
-        .sum();
+    /// `all`, `all-except:<chars>`, or `only:<chars>`.
+    impl FromStr for Symbols {
+        type Err = Error;
+
+        fn from_str(s: &str) -> Result<Self> {
+            match s.split_once(':') {
+                None if s == "all" => Ok(Symbols::puzzle()),
+                Some(("all-except", chars)) => Ok(Symbols::AllExcept(chars.chars().collect())),
+                Some(("only", chars)) => Ok(Symbols::Only(chars.chars().collect())),
+                _ => bail!("unknown symbols '{}', expected all, all-except:<chars> or only:<chars>", s)
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Combine {
+        Product,
+        Sum,
+        Min,
+        Max,
+    }
+
+    /// A gear is a `symbol` with exactly `adjacent` numbers around it, its ratio combines these
+    /// numbers.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct GearRule {
+        pub symbol: char,
+        pub adjacent: usize,
+        pub combine: Combine,
+    }
+
+    impl GearRule {
+        /// Any `*` next to exactly two numbers, the ratio is their product.
+        pub fn puzzle() -> Self {
+            GearRule { symbol: '*', adjacent: 2, combine: Combine::Product }
+        }
+
+        /// The gear ratio, or `None` if the symbol isn't a gear.
+        pub fn ratio(&self, symbol: char, numbers: &[u32]) -> Option<u64> {
+            if symbol != self.symbol || numbers.len() != self.adjacent {
+                return None;
+            }
+            let numbers = numbers.iter().map(|n| *n as u64);
+            match self.combine {
+                Combine::Product => Some(numbers.product()),
+                Combine::Sum => Some(numbers.sum()),
+                Combine::Min => numbers.min(),
+                Combine::Max => numbers.max(),
+            }
+        }
+    }
+
+    /// Written as `<symbol>:<adjacent>:<combine>`, e.g. `*:2:product`.
+    impl FromStr for GearRule {
+        type Err = Error;
+
+        fn from_str(s: &str) -> Result<Self> {
+            let mut fields = s.splitn(3, ':');
+            let (Some(symbol), Some(adjacent), Some(combine)) = (fields.next(), fields.next(), fields.next()) else {
+                bail!("expected <symbol>:<adjacent>:<combine>, got '{}'", s)
+            };
+            let mut symbol_chars = symbol.chars();
+            let (Some(symbol), None) = (symbol_chars.next(), symbol_chars.next()) else {
+                bail!("'{}' is not a single symbol", symbol)
+            };
+            let adjacent = adjacent.parse()
+                .with_context(|| format!("'{}' is not a number of adjacent numbers", adjacent))?;
+            let combine = match combine {
+                "product" => Combine::Product,
+                "sum" => Combine::Sum,
+                "min" => Combine::Min,
+                "max" => Combine::Max,
+                _ => bail!("unknown way to combine '{}', expected product, sum, min or max", combine)
+            };
+            Ok(GearRule { symbol, adjacent, combine })
+        }
+    }
+}
+
+mod render {
+    use advent_of_code_2023::visualize::{Color, Grid};
+    use crate::Plan;
+    use crate::rules::{GearRule, Symbols};
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Style {
+        Empty,
+        PartNumber,
+        OtherNumber,
+        Gear,
+        Symbol,
+    }
+
+    impl Style {
+        fn ansi(&self) -> &'static str {
+            match self {
+                Style::Empty => "\x1b[2m",
+                Style::PartNumber => "\x1b[32m",
+                Style::OtherNumber => "\x1b[31m",
+                Style::Gear => "\x1b[1;33m",
+                Style::Symbol => "\x1b[1;36m",
+            }
+        }
+
+        /// The same colours for images.
+        pub fn color(&self) -> Color {
+            match self {
+                Style::Empty => Color::rgb(40, 40, 40),
+                Style::PartNumber => Color::GREEN,
+                Style::OtherNumber => Color::RED,
+                Style::Gear => Color::YELLOW,
+                Style::Symbol => Color::CYAN,
+            }
+        }
+
+        fn css_class(&self) -> &'static str {
+            match self {
+                Style::Empty => "empty",
+                Style::PartNumber => "part",
+                Style::OtherNumber => "other",
+                Style::Gear => "gear",
+                Style::Symbol => "symbol",
+            }
+        }
+    }
+
+    /// How many numbers (or gears) there are of a kind, and what they add up to.
+    #[derive(Debug, Default, PartialEq)]
+    pub struct Total {
+        pub count: usize,
+        pub sum: u64,
+    }
+
+    #[derive(Debug, Default, PartialEq)]
+    pub struct Legend {
+        pub part_numbers: Total,
+        pub other_numbers: Total,
+        /// Summing up the gear ratios
+        pub gears: Total,
+    }
+
+    impl Legend {
+        fn lines(&self) -> [(Style, String); 3] {
+            [
+                (Style::PartNumber, format!("part numbers: {}, sum {}", self.part_numbers.count, self.part_numbers.sum)),
+                (Style::OtherNumber, format!("other numbers: {}, sum {}", self.other_numbers.count, self.other_numbers.sum)),
+                (Style::Gear, format!("gears: {}, sum of ratios {}", self.gears.count, self.gears.sum)),
+            ]
+        }
+    }
+
+    /// The schematic cell by cell, every cell with the style showing what it counts as. Symbols
+    /// that don't count are shown like empty cells.
+    #[derive(Debug)]
+    pub struct Annotated {
+        pub rows: Vec<Vec<(char, Style)>>,
+        pub legend: Legend,
+    }
+
+    pub fn annotate(plan: &Plan, symbols: &Symbols, gear_rule: &GearRule) -> Annotated {
+        let mut rows = vec![vec![('.', Style::Empty); plan.width]; plan.height];
+        let mut legend = Legend::default();
+
+        for symbol in plan.symbols.iter() {
+            rows[symbol.position.y][symbol.position.x] = (symbol.symbol, Style::Empty);
+        }
+        for symbol in plan.symbols(symbols) {
+            let style = match gear_rule.ratio(symbol.symbol, &symbol.numbers) {
+                Some(ratio) => {
+                    legend.gears.count += 1;
+                    legend.gears.sum += ratio;
+                    Style::Gear
+                }
+                None => Style::Symbol
+            };
+            rows[symbol.position.y][symbol.position.x] = (symbol.symbol, style);
+        }
+
+        for number in plan.numbers.iter() {
+            let (style, total) = if plan.is_part_number(number, symbols) {
+                (Style::PartNumber, &mut legend.part_numbers)
+            } else {
+                (Style::OtherNumber, &mut legend.other_numbers)
+            };
+            total.count += 1;
+            total.sum += number.value as u64;
+
+            // pad to the original length, in case of leading zeros
+            let digits = format!("{:0width$}", number.value, width = number.len);
+            for (offset, digit) in digits.chars().enumerate() {
+                rows[number.position.y][number.position.x + offset] = (digit, style);
+            }
+        }
+
+        Annotated { rows, legend }
+    }
+
+    impl Annotated {
+        /// Just the characters.
+        pub fn lines(&self) -> Vec<String> {
+            self.rows.iter().map(|row| row.iter().map(|(c, _)| c).collect()).collect()
+        }
+
+        pub fn grid(&self) -> Grid {
+            Grid::from_lines(&self.lines())
+        }
+
+        /// For terminals, styles only change where needed.
+        pub fn ansi(&self) -> String {
+            let mut rendered = String::new();
+            for row in self.rows.iter() {
+                let mut current = None;
+                for (c, style) in row.iter() {
+                    if current != Some(*style) {
+                        rendered.push_str("\x1b[0m");
+                        rendered.push_str(style.ansi());
+                        current = Some(*style);
+                    }
+                    rendered.push(*c);
+                }
+                rendered.push_str("\x1b[0m\n");
+            }
+            rendered.push('\n');
+            for (style, line) in self.legend.lines() {
+                rendered.push_str(&format!("{}■\x1b[0m {}\n", style.ansi(), line));
+            }
+            rendered
+        }
+
+        /// A standalone page with the schematic in a `pre` block.
+        pub fn html(&self) -> String {
+            let mut rendered = String::from(concat!(
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Engine schematic</title>\n<style>\n",
+                "body { background: #111; color: #ccc; font-family: monospace; }\n",
+                ".empty { color: #555; }\n.part { color: #4c4; }\n.other { color: #e44; }\n",
+                ".gear { color: #fd3; font-weight: bold; }\n.symbol { color: #4cd; font-weight: bold; }\n",
+                "</style>\n</head>\n<body>\n<pre>\n"
+            ));
+            for row in self.rows.iter() {
+                let mut current: Option<Style> = None;
+                for (c, style) in row.iter() {
+                    if current != Some(*style) {
+                        if current.is_some() {
+                            rendered.push_str("</span>");
+                        }
+                        rendered.push_str(&format!("<span class=\"{}\">", style.css_class()));
+                        current = Some(*style);
+                    }
+                    rendered.push_str(&escape(*c));
+                }
+                if current.is_some() {
+                    rendered.push_str("</span>");
+                }
+                rendered.push('\n');
+            }
+            rendered.push_str("</pre>\n<ul>\n");
+            for (style, line) in self.legend.lines() {
+                rendered.push_str(&format!("<li><span class=\"{}\">■</span> {}</li>\n", style.css_class(), line));
+            }
+            rendered.push_str("</ul>\n</body>\n</html>\n");
+            rendered
+        }
+    }
+
+    fn escape(c: char) -> String {
+        match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            _ => c.to_string()
+        }
+    }
+}
+
+mod animate {
+    use advent_of_code_2023::visualize::{Color, Frame, Grid, Overlay};
+    use anyhow::Result;
+    use crate::Plan;
+    use crate::render::annotate;
+    use crate::rules::{GearRule, Symbols};
+
+    /// The size of the frames, the schematic plus a status line.
+    pub fn frame_size(plan: &Plan) -> (usize, usize) {
+        (plan.width.max(40), plan.height + 1)
+    }
 
-    Ok((total_of_part_numbers, gear_rations))
+    /// One frame per number: the current number is marked, part numbers found so far are green,
+    /// other numbers red, and the status line keeps the running sum.
+    pub fn animate<F: FnMut(&Frame) -> Result<()>>(plan: &Plan, symbols: &Symbols, mut show: F) -> Result<()> {
+        let mut lines = annotate(plan, symbols, &GearRule::puzzle()).lines();
+        // room for the status line
+        lines.push(" ".repeat(frame_size(plan).0));
+        let grid = Grid::from_lines(&lines);
+
+        let mut part_cells = vec![];
+        let mut other_cells = vec![];
+        let mut sum = 0_u64;
+        for (idx, number) in plan.numbers.iter().enumerate() {
+            let cells: Vec<(usize, usize)> = (0..number.len)
+                .map(|offset| (number.position.x + offset, number.position.y))
+                .collect();
+            let touched: Vec<(usize, usize)> = number.adjacent()
+                .filter(|p| plan.symbol_at(*p, symbols).is_some())
+                .map(|p| (p.x, p.y))
+                .collect();
+
+            if touched.is_empty() {
+                other_cells.extend(cells.iter().copied());
+            } else {
+                part_cells.extend(cells.iter().copied());
+                sum += number.value as u64;
+            }
+
+            let status = format!("number {}/{}: {}, sum {}", idx + 1, plan.numbers.len(), number.value, sum);
+            let frame = Frame::new(grid.clone())
+                .with(Overlay::Highlight { cells: part_cells.clone(), color: Color::GREEN })
+                .with(Overlay::Highlight { cells: other_cells.clone(), color: Color::RED })
+                .with(Overlay::Highlight { cells: touched, color: Color::CYAN })
+                .with(Overlay::Path { points: cells, color: Color::BLUE })
+                .with(Overlay::Label { x: 0, y: plan.height, text: status, color: Color::YELLOW });
+            show(&frame)?;
+        }
+        Ok(())
+    }
 }
 
+fn solve_with(filename: &str, symbols: &Symbols, gear_rule: &GearRule) -> Result<(u32, u64)> {
+    let plan = parse_input(filename)?;
+
+    let total_of_part_numbers = plan.part_numbers(symbols).iter().sum();
+    let gear_ratios = plan.symbols(symbols).iter()
+        .filter_map(|symbol| gear_rule.ratio(symbol.symbol, &symbol.numbers))
+        .sum();
+
+    Ok((total_of_part_numbers, gear_ratios))
+}
 
 fn main() -> Result<()> {
-    let (r1, r2) = solve("src/day_03/input.txt")?;
+    let args: Vec<String> = env::args().collect();
+    let symbols = match flag_value(&args, "--symbols") {
+        Some(symbols) => symbols.parse()?,
+        None => Symbols::puzzle()
+    };
+    let gear_rule = match flag_value(&args, "--gear") {
+        Some(gear_rule) => gear_rule.parse()?,
+        None => GearRule::puzzle()
+    };
+    let filename = flag_value(&args, "--input").unwrap_or("src/day_03/input.txt");
+
+    if args.iter().any(|arg| arg == "--visualize") {
+        let plan = parse_input(filename)?;
+        let delay = Duration::from_millis(flag_value(&args, "--delay").map(str::parse).transpose()?.unwrap_or(50));
+        match flag_value(&args, "--cast") {
+            Some(cast) => {
+                let (width, height) = animate::frame_size(&plan);
+                let file = BufWriter::new(File::create(cast).with_context(|| format!("can't create {}", cast))?);
+                let mut visualizer = Visualizer::asciicast(file, width, height, delay, "Day 3: Gear Ratios")?;
+                animate::animate(&plan, &symbols, |frame| visualizer.show(frame))?;
+                println!("Recorded {} frames to {}", visualizer.frames(), cast);
+            }
+            None => match flag_value(&args, "--gif") {
+                Some(gif) => {
+                    // keep the GIF at about 100 frames, always including the final one
+                    let every = plan.numbers.len().div_ceil(100).max(1);
+                    let mut images = vec![];
+                    let mut idx = 0;
+                    animate::animate(&plan, &symbols, |frame| {
+                        idx += 1;
+                        if idx % every == 0 || idx == plan.numbers.len() {
+                            images.push(Image::from_frame(frame, 3, Color::BLACK));
+                        }
+                        Ok(())
+                    })?;
+                    let mut file = BufWriter::new(File::create(gif).with_context(|| format!("can't create {}", gif))?);
+                    write_gif(&mut file, &images, delay.as_millis() as u32)?;
+                    println!("Wrote {} frames to {}", images.len(), gif);
+                }
+                None => {
+                    let mut visualizer = Visualizer::terminal(delay);
+                    animate::animate(&plan, &symbols, |frame| visualizer.show(frame))?
+                }
+            }
+        }
+        return Ok(())
+    }
+    if let Some("bench") = args.get(1).map(String::as_str) {
+        let size = args.get(2).map(|size| size.parse()).transpose()?.unwrap_or(2_000);
+        return bench::run(size)
+    }
+    if let Some("render") = args.get(1).map(String::as_str) {
+        let annotated = render::annotate(&parse_input(filename)?, &symbols, &gear_rule);
+        let color_of = |x: usize, y: usize, _| annotated.rows[y][x].1.color();
+        if let Some(png) = flag_value(&args, "--png") {
+            let image = Image::from_grid(&annotated.grid(), 8, color_of);
+            image.write_png(&mut BufWriter::new(File::create(png).with_context(|| format!("can't create {}", png))?))?;
+            println!("Wrote a {}x{} image to {}", image.width, image.height, png);
+        } else if let Some(svg) = flag_value(&args, "--svg") {
+            let rendered = grid_svg(&annotated.grid(), 12, color_of, |c| c != '.');
+            fs::write(svg, rendered).with_context(|| format!("can't write {}", svg))?;
+            println!("Wrote {}", svg);
+        } else if args.iter().any(|arg| arg == "--html") {
+            print!("{}", annotated.html());
+        } else {
+            print!("{}", annotated.ansi());
+        }
+        return Ok(())
+    }
+    if let Some("symbols") = args.get(1).map(String::as_str) {
+        for symbol in parse_input(filename)?.symbols(&symbols) {
+            println!("{} at {},{}: {:?}", symbol.symbol, symbol.position.x, symbol.position.y, symbol.numbers);
+        }
+        return Ok(())
+    }
+
+    let (r1, r2) = solve_with(filename, &symbols, &gear_rule)?;
     println!("Result part 1: {}", r1);
     println!("Result part 2: {}", r2);
     Ok(())
@@ -151,41 +662,147 @@ fn main() -> Result<()> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{Number, Point, solve};
+    use crate::{bench, parse_input, solve_with, Number, Plan, Point, Symbol, SymbolNeighbours};
+    use crate::animate::animate;
+    use crate::render::{annotate, Legend, Style, Total};
+    use crate::rules::{Combine, GearRule, Symbols};
+    use std::time::Duration;
+    use advent_of_code_2023::visualize::Visualizer;
 
     #[test]
     fn solve_test_input_1() {
-        let result = solve("src/day_03/test_input.txt").unwrap();
+        let result = solve_with("src/day_03/test_input.txt", &Symbols::puzzle(), &GearRule::puzzle()).unwrap();
         assert_eq!(result, (4361, 467835));
     }
 
     #[test]
     fn test_adjacent_generator() {
+        let number = |digits: &str, x, y| Number { value: digits.parse().unwrap(), position: Point {x, y}, len: digits.len() };
+
+        assert_eq!(number("1", 3, 3).adjacent().count(), 8);
+        assert_eq!(number("123", 3, 3).adjacent().count(), 12);
+        assert_eq!(number("1", 0, 0).adjacent().count(), 3);
+        assert_eq!(number("467", 0, 0).adjacent().count(), 5);
+    }
+
+    #[test]
+    fn scan_schematic() {
+        let plan = Plan::scan("467..114..\n...*......\n..35.\n").unwrap();
+        assert_eq!((plan.width, plan.height), (10, 3));
+        assert_eq!(plan.numbers, vec![
+            Number { value: 467, position: Point {x: 0, y: 0}, len: 3 },
+            Number { value: 114, position: Point {x: 5, y: 0}, len: 3 },
+            Number { value: 35, position: Point {x: 2, y: 2}, len: 2 },
+        ]);
+        assert_eq!(plan.symbols, vec![Symbol { symbol: '*', position: Point {x: 3, y: 1} }]);
+
+        let error = Plan::scan("..1\n99999999999.").unwrap_err();
+        assert_eq!(error.to_string(), "line 2: number at column 1 too large");
+    }
+
+    #[test]
+    fn generated_schematics() {
+        let schematic = bench::generate(50, 3);
+        assert_eq!(schematic.lines().count(), 50);
+        assert!(schematic.lines().all(|line| line.len() == 50));
+
+        let plan = Plan::scan(&schematic).unwrap();
+        let part_sum: u32 = plan.part_numbers(&Symbols::puzzle()).iter().sum();
+        let touching_sum: u32 = plan.numbers.iter()
+            .filter(|number| plan.symbols.iter().any(|symbol| {
+                let p = symbol.position;
+                p.y + 1 >= number.position.y && p.y <= number.position.y + 1
+                    && p.x + 1 >= number.position.x && p.x <= number.position.x + number.len
+            }))
+            .map(|number| number.value)
+            .sum();
+        assert_eq!(part_sum, touching_sum);
+    }
+
+    #[test]
+    fn query_symbols() {
+        let plan = parse_input("src/day_03/test_input.txt").unwrap();
+        let symbols = plan.symbols(&Symbols::puzzle());
+
+        assert_eq!(symbols.len(), 6);
+        assert_eq!(symbols[0], SymbolNeighbours { symbol: '*', position: Point { x: 3, y: 1 }, numbers: vec![467, 35] });
+        assert_eq!(symbols[2], SymbolNeighbours { symbol: '*', position: Point { x: 3, y: 4 }, numbers: vec![617] });
+
+        let only_hash = plan.symbols(&"only:#".parse().unwrap());
+        assert_eq!(only_hash, vec![SymbolNeighbours { symbol: '#', position: Point { x: 6, y: 3 }, numbers: vec![633] }]);
+    }
 
-        assert_eq!(
-            Number::PartNumber("1".to_string()).adjacent(Point {x: 3, y: 3 }).len(),
-            8
-        );
-        assert_eq!(
-            Number::PartNumber("123".to_string()).adjacent(Point {x: 3, y: 3 }).len(),
-            12
-        );
-        assert_eq!(
-            Number::PartNumber("1".to_string()).adjacent(Point {x: 0, y: 0 }).len(),
-            3
-        );
-        assert_eq!(
-            Number::PartNumber("467".to_string()).adjacent(Point {x: 0, y: 0 }).len(),
-            5
-        );
-
-        assert_eq!(
-            Number::Emtpy.adjacent(Point {x: 3, y: 3 }).len(),
-            8
-        );
-        assert_eq!(
-            Number::Emtpy.adjacent(Point {x: 0, y: 0 }).len(),
-            3
-        );
-    }
-}
\ No newline at end of file
+    #[test]
+    fn configurable_rules() {
+        let sum_of_pairs: GearRule = "*:2:sum".parse().unwrap();
+        assert_eq!(sum_of_pairs, GearRule { symbol: '*', adjacent: 2, combine: Combine::Sum });
+        let result = solve_with("src/day_03/test_input.txt", &Symbols::puzzle(), &sum_of_pairs).unwrap();
+        assert_eq!(result, (4361, 467 + 35 + 755 + 598));
+
+        let single_star: GearRule = "*:1:max".parse().unwrap();
+        let result = solve_with("src/day_03/test_input.txt", &Symbols::puzzle(), &single_star).unwrap();
+        assert_eq!(result.1, 617);
+
+        let no_stars: Symbols = "all-except:*".parse().unwrap();
+        let result = solve_with("src/day_03/test_input.txt", &no_stars, &GearRule::puzzle()).unwrap();
+        assert_eq!(result, (4361 - 467 - 35 - 617 - 755 - 598, 0));
+
+        assert!("*:two:sum".parse::<GearRule>().is_err());
+        assert!("**:2:sum".parse::<GearRule>().is_err());
+        assert!("*:2:median".parse::<GearRule>().is_err());
+        assert!("some".parse::<Symbols>().is_err());
+    }
+
+    #[test]
+    fn number_touching_two_symbols_counts_once() {
+        let result = solve_with("src/day_03/test_input_shared.txt", &Symbols::puzzle(), &GearRule::puzzle()).unwrap();
+        assert_eq!(result, (12 + 3, 36));
+    }
+
+    #[test]
+    fn annotate_schematic() {
+        let plan = parse_input("src/day_03/test_input.txt").unwrap();
+        let annotated = annotate(&plan, &Symbols::puzzle(), &GearRule::puzzle());
+
+        assert_eq!(annotated.legend, Legend {
+            part_numbers: Total { count: 8, sum: 4361 },
+            other_numbers: Total { count: 2, sum: 114 + 58 },
+            gears: Total { count: 2, sum: 467835 },
+        });
+        assert_eq!(annotated.rows[0][0], ('4', Style::PartNumber));
+        assert_eq!(annotated.rows[0][5], ('1', Style::OtherNumber));
+        assert_eq!(annotated.rows[1][3], ('*', Style::Gear));
+        assert_eq!(annotated.rows[4][3], ('*', Style::Symbol));
+        assert_eq!(annotated.rows[0][3], ('.', Style::Empty));
+
+        let ansi = annotated.ansi();
+        assert!(ansi.starts_with("\x1b[0m\x1b[32m467\x1b[0m\x1b[2m..\x1b[0m\x1b[31m114"));
+        assert!(ansi.ends_with("gears: 2, sum of ratios 467835\n"));
+
+        let html = annotated.html();
+        assert!(html.contains("<span class=\"empty\">...</span><span class=\"gear\">*</span>"));
+        assert!(html.contains("<li><span class=\"part\">■</span> part numbers: 8, sum 4361</li>"));
+    }
+
+    #[test]
+    fn annotate_keeps_the_schematic() {
+        let plan = Plan::scan("007.\n.&<.\n#...").unwrap();
+        let annotated = annotate(&plan, &"all-except:#".parse().unwrap(), &GearRule::puzzle());
+
+        assert_eq!(annotated.lines(), vec!["007.", ".&<.", "#..."]);
+        assert_eq!(annotated.rows[2][0], ('#', Style::Empty));
+        assert!(annotated.html().contains("&amp;&lt;"));
+    }
+
+    #[test]
+    fn record_animation() {
+        let plan = parse_input("src/day_03/test_input.txt").unwrap();
+        let mut visualizer = Visualizer::asciicast(Vec::new(), 40, 11, Duration::from_millis(10), "test").unwrap();
+        animate(&plan, &Symbols::puzzle(), |frame| visualizer.show(frame)).unwrap();
+        assert_eq!(visualizer.frames(), 10);
+
+        let recorded = String::from_utf8(visualizer.into_inner().unwrap()).unwrap();
+        assert_eq!(recorded.lines().count(), 11);
+        assert!(recorded.lines().last().unwrap().contains("number 10/10: 598, sum 4361"));
+    }
+}
@@ -1,5 +1,8 @@
-use std::collections::HashMap;
-use anyhow::Result;
+use std::cmp::min;
+use std::env;
+use std::fs::File;
+use std::io::BufWriter;
+use anyhow::{Context, Result};
 
 #[macro_use]
 extern crate simple_log;
@@ -14,75 +17,412 @@ mod parse {
     pub struct Card {
         pub id: u32,
         pub winning_numbers: HashSet<u32>,
-        pub selected_numbers: HashSet<u32>
+        pub selected_numbers: HashSet<u32>,
+        /// Numbers that appeared more than once in the winning or selected list
+        pub duplicate_winning_numbers: Vec<u32>,
+        pub duplicate_selected_numbers: Vec<u32>,
     }
 
     impl Card {
-        pub fn num_matches(self: &Self) -> u32 {
+        pub fn new(id: u32, winning_numbers: Vec<u32>, selected_numbers: Vec<u32>) -> Self {
+            let (winning_numbers, duplicate_winning_numbers) = to_set(winning_numbers);
+            let (selected_numbers, duplicate_selected_numbers) = to_set(selected_numbers);
+            Card {
+                id,
+                winning_numbers,
+                selected_numbers,
+                duplicate_winning_numbers,
+                duplicate_selected_numbers
+            }
+        }
+
+        pub fn num_matches(&self) -> u32 {
             self.winning_numbers.intersection(&self.selected_numbers).count() as u32
         }
+
+        pub fn matching_numbers(&self) -> Vec<u32> {
+            let mut matching: Vec<u32> = self.winning_numbers.intersection(&self.selected_numbers)
+                .copied()
+                .collect();
+            matching.sort();
+            matching
+        }
+
+        /// The first match makes the card worth one point and each match after the first
+        /// doubles the point value of that card.
+        pub fn points(&self) -> u32 {
+            match self.num_matches() {
+                0 => 0,
+                matches => 2_u32.pow(matches - 1)
+            }
+        }
     }
 
     pub fn parse_input(filename: &str) -> Result<Vec<Card>> {
 
         let number_parser = parser!(nums:repeat_sep(u32, " "+) => nums);
-        let line_parser = parser!("Card" " "+ id:u32 ":" " "+ winning_numbers:number_parser " |" " "+ drawn_numbers:number_parser => Card {
-            id,
-            winning_numbers: HashSet::from_iter(winning_numbers.into_iter()),
-            selected_numbers: HashSet::from_iter(drawn_numbers.into_iter())
-        });
+        let line_parser = parser!("Card" " "+ id:u32 ":" " "+ winning_numbers:number_parser " |" " "+ drawn_numbers:number_parser
+            => Card::new(id, winning_numbers, drawn_numbers));
         let parser = parser!(lines(line_parser));
 
         let raw_data = read_to_string(filename)?;
         parser.parse(&raw_data).context("parse error")
     }
+
+    /// Collects the numbers into a set, also returning the ones that were dropped as duplicates.
+    fn to_set(numbers: Vec<u32>) -> (HashSet<u32>, Vec<u32>) {
+        let mut set = HashSet::with_capacity(numbers.len());
+        let mut duplicates = vec![];
+        for number in numbers {
+            if !set.insert(number) {
+                duplicates.push(number);
+            }
+        }
+        (set, duplicates)
+    }
 }
 
 fn solve_part_1(filename: &str) -> Result<u32> {
     let input = parse::parse_input(filename)?;
     let total: u32 = input.iter()
-        .map(|card| card.num_matches())
-        .filter(|matches| matches > &0)
-        .map(|matches| 2_u32.pow(matches - 1))
+        .map(|card| card.points())
         .sum();
 
     Ok(total)
 }
 
-fn solve_part_2(filename: &str) -> Result<u32> {
-    let input = parse::parse_input(filename)?;
-    let mut total = input.len() as u32;
-    let mut copies: HashMap<u32, u32> = HashMap::with_capacity(input.len() + 1);
+mod validate {
+    use std::collections::HashSet;
+    use std::fmt;
+    use std::fmt::Formatter;
+    use anyhow::{bail, Result};
+    use crate::parse::Card;
+
+    #[derive(Debug, PartialEq)]
+    pub enum Problem {
+        DuplicateWinningNumber { card: u32, number: u32 },
+        DuplicateSelectedNumber { card: u32, number: u32 },
+        RepeatedId { card: u32 },
+        UnexpectedId { expected: u32, found: u32 },
+        WinsBeyondLastCard { card: u32, matches: u32, cards_left: u32 },
+    }
+
+    impl fmt::Display for Problem {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            match self {
+                Problem::DuplicateWinningNumber { card, number } =>
+                    write!(f, "card {} lists winning number {} more than once", card, number),
+                Problem::DuplicateSelectedNumber { card, number } =>
+                    write!(f, "card {} lists selected number {} more than once", card, number),
+                Problem::RepeatedId { card } =>
+                    write!(f, "card {} appears more than once", card),
+                Problem::UnexpectedId { expected, found } =>
+                    write!(f, "expected card {} but found card {}", expected, found),
+                Problem::WinsBeyondLastCard { card, matches, cards_left } =>
+                    write!(f, "card {} has {} matches but only {} cards follow it", card, matches, cards_left),
+            }
+        }
+    }
+
+    /// Finds everything in the input that breaks the assumptions of the solvers: cards are
+    /// expected to be numbered consecutively starting at 1, to not repeat numbers within a list
+    /// and to never win copies of cards past the end of the table.
+    pub fn validate(cards: &[Card]) -> Vec<Problem> {
+        let mut problems = vec![];
+        let mut seen_ids = HashSet::new();
+        let mut previous_id = None;
+
+        for (idx, card) in cards.iter().enumerate() {
+            for number in card.duplicate_winning_numbers.iter() {
+                problems.push(Problem::DuplicateWinningNumber { card: card.id, number: *number });
+            }
+            for number in card.duplicate_selected_numbers.iter() {
+                problems.push(Problem::DuplicateSelectedNumber { card: card.id, number: *number });
+            }
+
+            // compared to the previous card, so a gap in the ids is only reported once
+            let expected = previous_id.map_or(1, |id| id + 1);
+            if !seen_ids.insert(card.id) {
+                problems.push(Problem::RepeatedId { card: card.id });
+            } else if card.id != expected {
+                problems.push(Problem::UnexpectedId { expected, found: card.id });
+            }
+            previous_id = Some(card.id);
+
+            let cards_left = (cards.len() - idx - 1) as u32;
+            if card.num_matches() > cards_left {
+                problems.push(Problem::WinsBeyondLastCard { card: card.id, matches: card.num_matches(), cards_left });
+            }
+        }
+
+        problems
+    }
+
+    /// Logs all problems as warnings, or fails if there are any and we are `strict`.
+    pub fn check(cards: &[Card], strict: bool) -> Result<()> {
+        let problems = validate(cards);
+        if strict && !problems.is_empty() {
+            let messages: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+            bail!("invalid input: {}", messages.join(", "));
+        }
+        for problem in problems {
+            warn!("{}", problem);
+        }
+        Ok(())
+    }
+}
+
+mod generate {
+    use std::fs::File;
+    use std::io::{BufWriter, Write};
+    use advent_of_code_2023::random::Rng;
+    use anyhow::{ensure, Result};
+
+    /// Writes `count` random scratchcards in puzzle format. Each card has 10 winning and 25
+    /// selected numbers out of 1..=99, with 0 to `max_matches` of them matching. Like in the
+    /// real input, wins never reference cards beyond the last one.
+    pub fn write_cards(filename: &str, count: usize, max_matches: usize, seed: u64) -> Result<()> {
+        ensure!(max_matches <= 10, "a card can't have more than 10 matches, got {}", max_matches);
+        let mut rng = Rng::new(seed);
+        let mut out = BufWriter::new(File::create(filename)?);
+
+        for id in 1..=count {
+            let mut pool: Vec<u32> = (1..=99).collect();
+            // partial Fisher-Yates, we only need the first 35 numbers to be random
+            for i in 0..35 {
+                let j = i + rng.next(pool.len() - i);
+                pool.swap(i, j);
+            }
+            let matches = rng.next(max_matches + 1).min(count - id);
+
+            let winning = &pool[0..10];
+            let mut selected: Vec<u32> = winning[0..matches].iter()
+                .chain(pool[10..(35 - matches)].iter())
+                .copied()
+                .collect();
+            for i in (1..selected.len()).rev() {
+                selected.swap(i, rng.next(i + 1));
+            }
+
+            let format = |numbers: &[u32]| numbers.iter()
+                .map(|n| format!("{:>2}", n))
+                .collect::<Vec<String>>()
+                .join(" ");
+            writeln!(out, "Card {:>4}: {} | {}", id, format(winning), format(&selected))?;
+        }
+
+        out.flush()?;
+        Ok(())
+    }
+}
+
+mod report {
+    use std::cmp::{min, Reverse};
+    use std::collections::BTreeMap;
+    use std::io::Write;
+    use anyhow::{Context, Result};
+    use crate::parse::Card;
+
+    #[derive(Debug)]
+    pub struct CardReport {
+        pub id: u32,
+        pub matching_numbers: Vec<u32>,
+        pub points: u32,
+        /// Originals plus won copies
+        pub copies: u64,
+        /// Card ids we won copies from, with the amount of copies won from each
+        pub won_from: Vec<(u32, u64)>,
+    }
+
+    impl CardReport {
+        /// How many cards all copies of this card won together.
+        pub fn cards_won(&self) -> Result<u64> {
+            self.copies.checked_mul(self.matching_numbers.len() as u64)
+                .with_context(|| format!("card {} won too many cards", self.id))
+        }
+    }
+
+    #[derive(Debug)]
+    pub struct Report {
+        pub cards: Vec<CardReport>,
+        /// Number of cards for each match count
+        pub match_distribution: BTreeMap<usize, usize>,
+        /// Generation 0 are the original cards, generation 1 the copies won by the originals,
+        /// generation 2 the copies won by those copies and so on.
+        pub cards_by_generation: Vec<u64>,
+    }
+
+    impl Report {
+        pub fn new(cards: &[Card]) -> Result<Self> {
+            let mut reports: Vec<CardReport> = cards.iter()
+                .map(|card| CardReport {
+                    id: card.id,
+                    matching_numbers: card.matching_numbers(),
+                    points: card.points(),
+                    copies: 0,
+                    won_from: vec![],
+                })
+                .collect();
+
+            // copies of each card, split up by generation
+            let mut generations: Vec<Vec<u64>> = vec![vec![1]; cards.len()];
+            let mut cards_by_generation: Vec<u64> = vec![];
+
+            for idx in 0..reports.len() {
+                let own_generations = generations[idx].clone();
+                for (generation, copies) in own_generations.iter().enumerate() {
+                    if cards_by_generation.len() <= generation {
+                        cards_by_generation.push(0);
+                    }
+                    cards_by_generation[generation] = cards_by_generation[generation]
+                        .checked_add(*copies).context("too many cards")?;
+                }
+                let copies = own_generations.iter()
+                    .try_fold(0_u64, |total, c| total.checked_add(*c))
+                    .context("too many copies")?;
+                reports[idx].copies = copies;
+
+                let id = reports[idx].id;
+                let last_won = min(idx + reports[idx].matching_numbers.len(), cards.len() - 1);
+                for next in (idx + 1)..=last_won {
+                    let next_generations = &mut generations[next];
+                    if next_generations.len() < own_generations.len() + 1 {
+                        next_generations.resize(own_generations.len() + 1, 0);
+                    }
+                    for (generation, copies) in own_generations.iter().enumerate() {
+                        next_generations[generation + 1] = next_generations[generation + 1]
+                            .checked_add(*copies).context("too many copies")?;
+                    }
+                    reports[next].won_from.push((id, copies));
+                }
+            }
+
+            let match_distribution = reports.iter()
+                .fold(BTreeMap::new(), |mut distribution, card| {
+                    *distribution.entry(card.matching_numbers.len()).or_insert(0) += 1;
+                    distribution
+                });
 
-    for card in input.into_iter() {
-        let matches = card.num_matches();
-        debug!("Original card {} has {} wins.", card.id, matches);
+            Ok(Report {
+                cards: reports,
+                match_distribution,
+                cards_by_generation,
+            })
+        }
+
+        pub fn total_cards(&self) -> Result<u64> {
+            self.cards_by_generation.iter()
+                .try_fold(0_u64, |total, count| total.checked_add(*count))
+                .context("too many cards")
+        }
+
+        /// The card that won the most cards, the earlier one if there's a tie.
+        pub fn most_valuable(&self) -> Result<Option<&CardReport>> {
+            let cards_won = self.cards.iter()
+                .map(|card| card.cards_won())
+                .collect::<Result<Vec<u64>>>()?;
+            Ok(self.cards.iter()
+                .zip(cards_won)
+                .max_by_key(|(card, cards_won)| (*cards_won, Reverse(card.id)))
+                .map(|(card, _)| card))
+        }
 
-        for next_card_num in (card.id + 1)..(card.id + 1 + matches) {
-            *copies.entry(next_card_num).or_insert(0) += 1;
-            let copy_count = copies.get(&next_card_num).expect("card entry exists");
-            debug!("  updated count for {} to {}", next_card_num, copy_count);
+        pub fn log(&self) -> Result<()> {
+            for card in self.cards.iter() {
+                info!("Card {}: matches {:?}, {} points, {} copies, won from {:?}",
+                    card.id, card.matching_numbers, card.points, card.copies, card.won_from);
+            }
+            for (matches, count) in self.match_distribution.iter() {
+                info!("{} cards with {} matches", count, matches);
+            }
+            for (generation, count) in self.cards_by_generation.iter().enumerate() {
+                info!("{} cards in generation {}", count, generation);
+            }
+            if let Some(card) = self.most_valuable()? {
+                info!("Most valuable is card {} which won {} cards", card.id, card.cards_won()?);
+            }
+            info!("{} cards in total", self.total_cards()?);
+            Ok(())
         }
 
-        let copy_count = *copies.get(&card.id).unwrap_or(&0);
-        debug!(" Card {} has {} copies.", card.id, copy_count);
-        for _ in 0..copy_count {
-            for next_card_num in (card.id + 1)..(card.id + 1 + matches) {
-                *copies.entry(next_card_num).or_insert(0) += 1;
-                let copy_count = copies.get(&next_card_num).expect("card entry exists");
-                debug!("  updated count for {} to {}", next_card_num, copy_count);
+        /// One row per card. Lists are separated by spaces, `won_from` entries are written
+        /// as `<card id>x<copies>`.
+        pub fn write_csv<W: Write>(&self, out: &mut W) -> Result<()> {
+            writeln!(out, "id,matches,matching_numbers,points,copies,cards_won,won_from")?;
+            for card in self.cards.iter() {
+                let matching_numbers = card.matching_numbers.iter()
+                    .map(|n| n.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                let won_from = card.won_from.iter()
+                    .map(|(id, copies)| format!("{}x{}", id, copies))
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                writeln!(out, "{},{},{},{},{},{},{}", card.id, card.matching_numbers.len(),
+                         matching_numbers, card.points, card.copies, card.cards_won()?, won_from)?;
             }
+            Ok(())
         }
     }
+}
+
+/// Every card wins one copy of each of the next `num_matches` cards for every copy we already
+/// have of it. Instead of handing out copies card by card, we keep track of how many copies are
+/// currently being won and when those wins run out, so every card is visited exactly once.
+fn count_cards(cards: &[parse::Card]) -> Result<u64> {
+    let mut expiring = vec![0_u64; cards.len() + 1];
+    let mut won_copies = 0_u64;
+    let mut total = 0_u64;
+
+    for (idx, card) in cards.iter().enumerate() {
+        won_copies -= expiring[idx];
+        let copies = won_copies.checked_add(1).context("too many copies")?;
+        debug!("Card {} has {} copies and {} wins.", card.id, copies, card.num_matches());
+        total = total.checked_add(copies).context("too many cards in total")?;
 
-    total += copies.values().sum::<u32>();
+        let last_won = min(idx + card.num_matches() as usize, cards.len() - 1);
+        if last_won > idx {
+            won_copies = won_copies.checked_add(copies).context("too many copies")?;
+            expiring[last_won + 1] += copies;
+        }
+    }
 
     Ok(total)
 }
 
+fn solve_part_2(filename: &str) -> Result<u64> {
+    let input = parse::parse_input(filename)?;
+    count_cards(&input)
+}
+
 fn main() -> Result<()> {
     simple_log::quick!("info");
 
+    let args: Vec<String> = env::args().collect();
+    let strict = args.iter().any(|arg| arg == "--strict");
+    let args: Vec<String> = args.into_iter().filter(|arg| !arg.starts_with("--")).collect();
+    if let Some("generate") = args.get(1).map(String::as_str) {
+        let filename = args.get(2).context("usage: day_04 generate <file> <count> [max matches]")?;
+        let count = args.get(3).context("missing card count")?.parse()?;
+        let max_matches = args.get(4).map(|m| m.parse()).unwrap_or(Ok(1))?;
+        generate::write_cards(filename, count, max_matches, 42)?;
+        info!("Wrote {} cards to {}", count, filename);
+        return Ok(())
+    }
+    if let Some("report") = args.get(1).map(String::as_str) {
+        let filename = args.get(2).map(String::as_str).unwrap_or("src/day_04/input.txt");
+        let cards = parse::parse_input(filename)?;
+        validate::check(&cards, strict)?;
+        let report = report::Report::new(&cards)?;
+        report.log()?;
+        if let Some(csv_filename) = args.get(3) {
+            report.write_csv(&mut BufWriter::new(File::create(csv_filename)?))?;
+            info!("Wrote report to {}", csv_filename);
+        }
+        return Ok(())
+    }
+
+    validate::check(&parse::parse_input("src/day_04/input.txt")?, strict)?;
     info!("Result part 1: {}", solve_part_1("src/day_04/input.txt")?);
     info!("Result part 2: {}", solve_part_2("src/day_04/input.txt")?);
     Ok(())
@@ -91,7 +431,14 @@ fn main() -> Result<()> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{solve_part_1, solve_part_2};
+    use std::collections::BTreeMap;
+    use std::env::temp_dir;
+    use std::fs::remove_file;
+    use std::path::PathBuf;
+    use std::process;
+    use crate::{count_cards, generate, parse, solve_part_1, solve_part_2};
+    use crate::report::{CardReport, Report};
+    use crate::validate::{check, validate, Problem};
 
     #[test]
     fn solve_test_input_1() {
@@ -104,4 +451,120 @@ mod tests {
         let result = solve_part_2("src/day_04/test_input.txt").unwrap();
         assert_eq!(result, 30);
     }
-}
\ No newline at end of file
+
+    /// Hands out copies one card at a time, just like the puzzle describes it.
+    fn count_cards_naive(cards: &[parse::Card]) -> u64 {
+        let mut copies = vec![1_u64; cards.len()];
+        for (idx, card) in cards.iter().enumerate() {
+            for next in (idx + 1)..=(idx + card.num_matches() as usize) {
+                copies[next] += copies[idx];
+            }
+        }
+        copies.iter().sum()
+    }
+
+    /// A file in the temp dir that is unique to this test run and gets removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            TempFile(temp_dir().join(format!("day_04_{}_{}.txt", name, process::id())))
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn solve_generated_input() {
+        let file = TempFile::new("generated");
+        let filename = file.path();
+        generate::write_cards(filename, 20_000, 1, 4).unwrap();
+
+        let cards = parse::parse_input(filename).unwrap();
+        assert_eq!(cards.len(), 20_000);
+        assert!(cards.iter().all(|c| c.winning_numbers.len() == 10 && c.selected_numbers.len() == 25));
+        assert_eq!(count_cards(&cards).unwrap(), count_cards_naive(&cards));
+    }
+
+    #[test]
+    fn too_many_copies_is_an_error() {
+        let file = TempFile::new("exploding");
+        let filename = file.path();
+        generate::write_cards(filename, 1_000, 10, 4).unwrap();
+
+        let result = solve_part_2(filename);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn generate_more_matches_than_winning_numbers() {
+        let file = TempFile::new("too_many_matches");
+        assert!(generate::write_cards(file.path(), 100, 11, 4).is_err());
+    }
+
+    #[test]
+    fn report_test_input() {
+        let report = Report::new(&parse::parse_input("src/day_04/test_input.txt").unwrap()).unwrap();
+
+        assert_eq!(report.cards[0].matching_numbers, vec![17, 48, 83, 86]);
+        assert_eq!(report.cards[0].points, 8);
+        assert_eq!(report.cards[3].copies, 8);
+        assert_eq!(report.cards[3].won_from, vec![(1, 1), (2, 2), (3, 4)]);
+        assert_eq!(report.match_distribution.get(&0), Some(&2));
+        assert_eq!(report.cards_by_generation, vec![6, 9, 9, 5, 1]);
+        assert_eq!(report.total_cards().unwrap(), 30);
+        assert_eq!(report.most_valuable().unwrap().unwrap().id, 3);
+
+        let mut csv = vec![];
+        report.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("id,matches,matching_numbers,points,copies,cards_won,won_from"));
+        assert_eq!(lines.nth(3), Some("4,1,84,1,8,8,1x1 2x2 3x4"));
+    }
+
+    #[test]
+    fn report_overflow_is_an_error() {
+        let card = CardReport { id: 7, matching_numbers: vec![1, 2], points: 2, copies: u64::MAX / 2 + 1, won_from: vec![] };
+        assert_eq!(card.cards_won().unwrap_err().to_string(), "card 7 won too many cards");
+
+        let report = Report { cards: vec![card], match_distribution: BTreeMap::new(), cards_by_generation: vec![u64::MAX, 1] };
+        assert!(report.total_cards().is_err());
+        assert!(report.most_valuable().is_err());
+    }
+
+    #[test]
+    fn validate_test_input() {
+        let cards = parse::parse_input("src/day_04/test_input.txt").unwrap();
+        assert_eq!(validate(&cards), vec![]);
+        assert!(check(&cards, true).is_ok());
+    }
+
+    #[test]
+    fn validate_invalid_input() {
+        let cards = parse::parse_input("src/day_04/test_input_invalid.txt").unwrap();
+        assert_eq!(validate(&cards), vec![
+            Problem::DuplicateWinningNumber { card: 1, number: 48 },
+            Problem::DuplicateSelectedNumber { card: 1, number: 86 },
+            Problem::UnexpectedId { expected: 3, found: 4 },
+            Problem::RepeatedId { card: 4 },
+            Problem::WinsBeyondLastCard { card: 4, matches: 1, cards_left: 0 },
+        ]);
+        assert!(check(&cards, false).is_ok());
+        assert!(check(&cards, true).is_err());
+    }
+
+    #[test]
+    fn validate_gap_in_ids() {
+        let cards = parse::parse_input("src/day_04/test_input_gap.txt").unwrap();
+        assert_eq!(validate(&cards), vec![Problem::UnexpectedId { expected: 2, found: 3 }]);
+    }
+}
@@ -22,11 +22,11 @@ mod parse {
 
     impl Input {
         pub fn new(seeds: Vec<u64>, mappings: Vec<Mapping>) -> Self {
-            assert!(seeds.len() % 2 == 0);
+            assert!(seeds.len().is_multiple_of(2));
 
             let seed_ranges = seeds.chunks(2)
                 .map(|chunk| {
-                    let start = *chunk.get(0).expect("slice of size 2");
+                    let start = *chunk.first().expect("slice of size 2");
                     let length = *chunk.get(1).expect("slice of size 2");
                     start..(start + length)
                 })
@@ -44,8 +44,10 @@ mod parse {
 
     #[derive(Debug)]
     pub struct Mapping {
+        #[allow(dead_code)]
         pub name: String,
         pub ranges: Vec<MappingRange>,
+        #[allow(dead_code)]
         pub map_cache: HashMap<u64, u64>
     }
 
@@ -87,7 +89,7 @@ mod parse {
             .map(|(name, raw_ranges)| {
                 let ranges: Vec<MappingRange> = raw_ranges.into_iter()
                     .map(|raw_range: Vec<u64>| {
-                        let dest_start: u64 = *raw_range.get(0).expect("element at 0");
+                        let dest_start: u64 = *raw_range.first().expect("element at 0");
                         let source_start: u64 = *raw_range.get(1).expect("element at 1");
                         let length: u64 = *raw_range.get(2).expect("element at 2");
                         MappingRange::new(source_start, dest_start, length)
@@ -102,7 +104,8 @@ mod parse {
 }
 
 impl Mapping {
-    pub fn map(self: &mut Self, input: u64) -> u64 {
+    #[cfg(test)]
+    pub fn map(&self, input: u64) -> u64 {
         for range in self.ranges.iter() {
             if range.source.contains(&input) {
                 let offset = input - range.source.start;
@@ -114,7 +117,7 @@ impl Mapping {
         input
     }
 
-    pub fn map_rev(self: &Self, input: u64) -> u64 {
+    pub fn map_rev(&self, input: u64) -> u64 {
         for range in self.ranges.iter() {
             if range.dest.contains(&input) {
                 let offset = input - range.dest.start;
@@ -134,7 +137,7 @@ fn solve_part_1(filename: &str) -> Result<u64> {
         .map(|r| r.dest.end)
         .max().expect("a maximum value");
 
-    let seeds: HashSet<u64> = HashSet::from_iter(input.seeds.into_iter());
+    let seeds: HashSet<u64> = HashSet::from_iter(input.seeds);
     for dest in 0..limit {
         let mut current_number = dest;
 
@@ -1,5 +1,7 @@
-use std::ops::{RangeInclusive};
-use anyhow::{Result};
+use std::env;
+use advent_of_code_2023::common::flag_value;
+use anyhow::Result;
+use crate::boat::BoatModel;
 use crate::parse::Race;
 
 #[macro_use]
@@ -7,7 +9,7 @@ extern crate simple_log;
 
 mod parse {
     use aoc_parse::{parser, prelude::*};
-    use anyhow::{Result, Context};
+    use anyhow::{Result, Context, bail};
     use std::fs::read_to_string;
 
     #[derive(Debug)]
@@ -24,131 +26,336 @@ mod parse {
 
 
     pub fn parse_input(filename: &str) -> Result<Input> {
+        // keep the columns as digit strings, so we can both read them as individual numbers
+        // and glue them together for the race with bad kerning
+        let columns = parser!(repeat_sep(string(digit+), " "+));
         let parser = parser!(
-            line("Time:" " "+ repeat_sep(u32, " "+))
-            line("Distance:" " "+ repeat_sep(u32, " "+))
+            line("Time:" " "+ times:columns => times)
+            line("Distance:" " "+ distances:columns => distances)
         );
 
         let raw_data = read_to_string(filename)?;
-        let parsed = parser.parse(&raw_data).context("parse error")?;
+        let (times, distances) = parser.parse(&raw_data).context("parse error")?;
 
-        let (_, times) = parsed.0;
-        let (_, distance) = parsed.1;
-        assert_eq!(times.len(), distance.len());
+        if times.len() != distances.len() {
+            bail!("found {} times but {} distances, every race needs both", times.len(), distances.len());
+        }
 
-        let races: Vec<Race> = times.into_iter().zip(distance.into_iter())
-            .map(|(time, record_distance)| Race {
-                time: time as u64,
-                record_distance: record_distance as u64
-            })
-            .collect();
-
-        let mut actual_time = String::new();
-        let mut actual_distance = String::new();
-        races.iter().for_each(|r| {
-            actual_time.push_str(r.time.to_string().as_str());
-            actual_distance.push_str(r.record_distance.to_string().as_str());
-        });
+        let races = times.iter().zip(distances.iter())
+            .map(|(time, record_distance)| Ok(Race {
+                time: time.parse().with_context(|| format!("time {} too large", time))?,
+                record_distance: record_distance.parse()
+                    .with_context(|| format!("distance {} too large", record_distance))?
+            }))
+            .collect::<Result<Vec<Race>>>()?;
 
         Ok(Input {
-            races: races,
+            races,
             actual_race: Race {
-                time: actual_time.parse().context("actual_time not a number")?,
-                record_distance: actual_distance.parse().context("actual_distance not a number")?
+                time: concat_digits(&times).context("actual time too large")?,
+                record_distance: concat_digits(&distances).context("actual distance too large")?
             }
         })
     }
-}
 
-/// Your toy boat has a starting speed of zero millimeters per millisecond. For each whole
-/// millisecond you spend at the beginning of the race holding down the button, the boat's speed
-/// increases by one millimeter per millisecond.
-fn distance_traveled(x: &u64, race_time: &u64) -> u64 {
-    let travel_time = race_time - x;
-    let start_speed = x;
-    start_speed * travel_time // x * (race_time - x)
+    /// Reads all columns as if they were a single number, ignoring the whitespace in between.
+    fn concat_digits(columns: &[String]) -> Option<u64> {
+        columns.iter()
+            .flat_map(|column| column.chars())
+            .try_fold(0_u64, |number, c| number.checked_mul(10)?
+                .checked_add(c.to_digit(10)? as u64))
+    }
 }
 
-fn win_possible_interval(min_distance: &u64, race_time: &u64) -> RangeInclusive<u64> {
-    // f(x) = x * (T-x)         where x = hold time and T is total race time
-    //  we are only interested in solutions where we would win, so:
-    // f(x) = (x * (T-x)) - D   where D is the record to beat
-    //  solving for 0 gives us two solutions (https://www.wolframalpha.com/input?i=0+%3D+%28x+*+%28T-x%29%29+-+r)
-    // x = 1/2 (T - sqrt(T^2 - 4 D))
-    // x = 1/2 (sqrt(T^2 - 4 D) + T)
+mod boat {
+    use std::cmp::min;
+    use std::ops::RangeInclusive;
+    use advent_of_code_2023::common::flag_value;
+    use anyhow::{Context, Result};
+    use crate::parse::Race;
+
+    /// Describes how a boat reacts to the button being held down. For every whole millisecond
+    /// the button is held, the speed increases by `acceleration` millimeters per millisecond,
+    /// starting at `start_speed` and never exceeding `max_speed` (if there is one).
+    #[derive(Debug, Clone)]
+    pub struct BoatModel {
+        pub start_speed: u64,
+        pub acceleration: u64,
+        pub max_speed: Option<u64>,
+    }
+
+    impl Default for BoatModel {
+        /// Your toy boat has a starting speed of zero millimeters per millisecond. For each whole
+        /// millisecond you spend at the beginning of the race holding down the button, the boat's
+        /// speed increases by one millimeter per millisecond.
+        fn default() -> Self {
+            BoatModel {
+                start_speed: 0,
+                acceleration: 1,
+                max_speed: None,
+            }
+        }
+    }
 
-    let sqrt = |n: u64| (n as f64).sqrt();
+    impl BoatModel {
+        /// The default boat, with every field that is given as `--start-speed`, `--acceleration`
+        /// or `--max-speed` replaced.
+        pub fn from_args(args: &[String]) -> Result<Self> {
+            let value = |flag: &str| flag_value(args, flag)
+                .map(|value| value.parse::<u64>().with_context(|| format!("invalid {} '{}'", flag, value)))
+                .transpose();
 
-    let inner = sqrt(race_time.pow(2) - 4 * min_distance);
-    let race_time = *race_time as f64;
+            let default = BoatModel::default();
+            Ok(BoatModel {
+                start_speed: value("--start-speed")?.unwrap_or(default.start_speed),
+                acceleration: value("--acceleration")?.unwrap_or(default.acceleration),
+                max_speed: value("--max-speed")?.or(default.max_speed),
+            })
+        }
 
-    let result_1 = 0.5 * (race_time - inner);
-    let result_2 = 0.5 * (inner + race_time);
+        pub fn speed(&self, hold_time: u64) -> u128 {
+            let speed = self.start_speed as u128 + self.acceleration as u128 * hold_time as u128;
+            match self.max_speed {
+                Some(max_speed) => min(speed, max_speed as u128),
+                None => speed
+            }
+        }
 
-    (result_1.ceil().round() as u64)..=(result_2.floor() as u64)
-}
+        pub fn distance_traveled(&self, hold_time: u64, race_time: u64) -> u128 {
+            let travel_time = race_time.saturating_sub(hold_time);
+            self.speed(hold_time) * travel_time as u128
+        }
+
+        /// The speed cap only matters if we can actually reach it within the race.
+        fn is_quadratic(&self, race_time: u64) -> bool {
+            match self.max_speed {
+                Some(max_speed) => self.acceleration > 0 && self.speed(race_time) < max_speed as u128,
+                None => self.acceleration > 0
+            }
+        }
 
-fn winning_wait_times(race: &Race) -> u32 {
+        /// All hold times that beat the record of the given race, or `None` if the record can't
+        /// be beaten at all.
+        pub fn win_possible_interval(&self, race: &Race) -> Option<RangeInclusive<u64>> {
+            let interval = if self.is_quadratic(race.time) {
+                self.win_possible_interval_closed_form(race)
+            } else {
+                self.win_possible_interval_search(race)
+            }?;
 
-    (0..race.time)
-        .map(|hold_time| distance_traveled(&hold_time, &race.time))
-        .filter(|distance| distance > &race.record_distance)
-        .count() as u32
+            if interval.is_empty() {
+                None
+            } else {
+                Some(interval)
+            }
+        }
+
+        fn beats_record(&self, hold_time: u64, race: &Race) -> bool {
+            self.distance_traveled(hold_time, race.time) > race.record_distance as u128
+        }
+
+        fn win_possible_interval_closed_form(&self, race: &Race) -> Option<RangeInclusive<u64>> {
+            // f(x) = (s + a*x) * (T-x)       where x = hold time, s = start speed, a = acceleration
+            //                                 and T is total race time
+            //  we are only interested in solutions where we would win, so:
+            // f(x) = (s + a*x) * (T-x) - D   where D is the record to beat
+            //      = -a*x^2 + (a*T - s)*x + (s*T - D)
+            //  solving for 0 gives us two solutions
+            // x = ((a*T - s) -/+ sqrt((a*T - s)^2 + 4*a*(s*T - D))) / (2*a)
+            let a = self.acceleration as f64;
+            let s = self.start_speed as f64;
+            let t = race.time as f64;
+            let d = race.record_distance as f64;
+
+            let b = a * t - s;
+            let discriminant = b * b + 4.0 * a * (s * t - d);
+            if discriminant < 0.0 {
+                return None
+            }
+            let inner = discriminant.sqrt();
+
+            let result_1 = ((b - inner) / (2.0 * a)).ceil().clamp(0.0, t) as u64;
+            let result_2 = ((b + inner) / (2.0 * a)).floor().clamp(0.0, t) as u64;
+
+            // floating point math is only almost exact, so nudge the bounds until they are exact
+            let mut lower = result_1;
+            while lower > 0 && self.beats_record(lower - 1, race) {
+                lower -= 1;
+            }
+            while lower <= result_2 && !self.beats_record(lower, race) {
+                lower += 1;
+            }
+            let mut upper = result_2;
+            while upper < race.time && self.beats_record(upper + 1, race) {
+                upper += 1;
+            }
+            while upper >= lower && upper > 0 && !self.beats_record(upper, race) {
+                upper -= 1;
+            }
+
+            Some(lower..=upper)
+        }
+
+        fn win_possible_interval_search(&self, race: &Race) -> Option<RangeInclusive<u64>> {
+            // the distance function is unimodal: it grows while the gained speed is worth more
+            // than the lost travel time and shrinks from then on. So we first search the peak...
+            let is_rising = |x: u64| x < race.time
+                && self.distance_traveled(x + 1, race.time) > self.distance_traveled(x, race.time);
+            let peak = partition_point(0, race.time, is_rising);
+            if !self.beats_record(peak, race) {
+                return None
+            }
+
+            // ...and then the points on either side where we start/stop beating the record
+            let lower = partition_point(0, peak, |x| !self.beats_record(x, race));
+            let upper = partition_point(peak, race.time, |x| self.beats_record(x, race)) - 1;
+
+            Some(lower..=upper)
+        }
+    }
+
+    /// Returns the first value in `from..=to` for which `predicate` is false, assuming
+    /// the predicate is true for some prefix of the range and false for the rest. Returns `to + 1`
+    /// if the predicate holds for the whole range.
+    fn partition_point<F: Fn(u64) -> bool>(from: u64, to: u64, predicate: F) -> u64 {
+        let mut low = from;
+        let mut high = to + 1;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if predicate(mid) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+}
+
+fn winning_wait_times(race: &Race, boat: &BoatModel) -> u64 {
+    boat.win_possible_interval(race)
+        .map(|interval| interval.end() - interval.start() + 1)
+        .unwrap_or(0)
 }
 
-fn solve_part_1(filename: &str) -> Result<u32> {
+fn solve_part_1(filename: &str, boat: &BoatModel) -> Result<u64> {
     let input = parse::parse_input(filename)?;
 
     let total = input.races.iter()
-        .map(winning_wait_times)
-        .fold(1_u32, |total, winning_waits| total * winning_waits);
+        .map(|race| winning_wait_times(race, boat))
+        .product();
     Ok(total)
 }
 
-fn solve_part_2(filename: &str) -> Result<u32> {
+fn solve_part_2(filename: &str, boat: &BoatModel) -> Result<u64> {
     let input = parse::parse_input(filename)?;
-    Ok(winning_wait_times(&input.actual_race))
+    Ok(winning_wait_times(&input.actual_race, boat))
 }
 
 fn main() -> Result<()> {
     simple_log::quick!("info");
-    info!("Result part 1: {}", solve_part_1("src/day_06/input.txt")?);
-    info!("Result part 2: {}", solve_part_2("src/day_06/input.txt")?);
+    let args: Vec<String> = env::args().collect();
+    let boat = BoatModel::from_args(&args)?;
+    let filename = flag_value(&args, "--input").unwrap_or("src/day_06/input.txt");
+    info!("Result part 1: {}", solve_part_1(filename, &boat)?);
+    info!("Result part 2: {}", solve_part_2(filename, &boat)?);
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{distance_traveled, solve_part_1, solve_part_2, win_possible_interval};
+    use crate::{solve_part_1, solve_part_2};
+    use crate::boat::BoatModel;
+    use crate::parse::{parse_input, Race};
+
+    fn race(time: u64, record_distance: u64) -> Race {
+        Race { time, record_distance }
+    }
 
     #[test]
     fn solve_test_input_1() {
-        let result = solve_part_1("src/day_06/test_input.txt").unwrap();
+        let result = solve_part_1("src/day_06/test_input.txt", &BoatModel::default()).unwrap();
         assert_eq!(result, 288);
     }
 
     #[test]
     fn solve_test_input_2() {
-        let result = solve_part_2("src/day_06/test_input.txt").unwrap();
+        let result = solve_part_2("src/day_06/test_input.txt", &BoatModel::default()).unwrap();
         assert_eq!(result, 71503);
     }
 
+    #[test]
+    fn parse_large_numbers() {
+        let input = parse_input("src/day_06/test_input_large.txt").unwrap();
+        assert_eq!(input.races[1].time, 4294967296);
+        assert_eq!(input.actual_race.time, 74294967296);
+        assert_eq!(input.actual_race.record_distance, 9_000_000_000_001_234);
+    }
+
+    #[test]
+    fn parse_mismatched_columns() {
+        let error = parse_input("src/day_06/test_input_mismatched.txt").unwrap_err();
+        assert_eq!(error.to_string(), "found 3 times but 2 distances, every race needs both");
+    }
+
     #[test]
     fn test_distance_function() {
-        assert_eq!(distance_traveled(&0, &7), 0);
-        assert_eq!(distance_traveled(&1, &7), 6);
-        assert_eq!(distance_traveled(&2, &7), 10);
-        assert_eq!(distance_traveled(&3, &7), 12);
-        assert_eq!(distance_traveled(&4, &7), 12);
-        assert_eq!(distance_traveled(&5, &7), 10);
-        assert_eq!(distance_traveled(&6, &7), 6);
-        assert_eq!(distance_traveled(&7, &7), 0);
+        let boat = BoatModel::default();
+        assert_eq!(boat.distance_traveled(0, 7), 0);
+        assert_eq!(boat.distance_traveled(1, 7), 6);
+        assert_eq!(boat.distance_traveled(2, 7), 10);
+        assert_eq!(boat.distance_traveled(3, 7), 12);
+        assert_eq!(boat.distance_traveled(4, 7), 12);
+        assert_eq!(boat.distance_traveled(5, 7), 10);
+        assert_eq!(boat.distance_traveled(6, 7), 6);
+        assert_eq!(boat.distance_traveled(7, 7), 0);
     }
 
     #[test]
     fn test_win_possible_interval() {
-        assert_eq!(win_possible_interval(&9, &7), 2..=5);
-        assert_eq!(win_possible_interval(&40, &15), 4..=11);
-        assert_eq!(win_possible_interval(&200, &30), 10..=20);
+        let boat = BoatModel::default();
+        assert_eq!(boat.win_possible_interval(&race(7, 9)), Some(2..=5));
+        assert_eq!(boat.win_possible_interval(&race(15, 40)), Some(4..=11));
+        // holding 10ms or 20ms only ties the record
+        assert_eq!(boat.win_possible_interval(&race(30, 200)), Some(11..=19));
+        assert_eq!(boat.win_possible_interval(&race(7, 12)), None);
+    }
+
+    #[test]
+    fn boat_from_args() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>();
+
+        let boat = BoatModel::from_args(&args(&["day_06", "--acceleration", "2", "--max-speed", "9"])).unwrap();
+        assert_eq!((boat.start_speed, boat.acceleration, boat.max_speed), (0, 2, Some(9)));
+        let boat = BoatModel::from_args(&args(&["day_06", "--start-speed", "3"])).unwrap();
+        assert_eq!((boat.start_speed, boat.acceleration, boat.max_speed), (3, 1, None));
+
+        let error = BoatModel::from_args(&args(&["day_06", "--max-speed", "fast"])).unwrap_err();
+        assert_eq!(error.to_string(), "invalid --max-speed 'fast'");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_win_possible_interval_matches_brute_force() {
+        let boats = vec![
+            BoatModel::default(),
+            BoatModel { start_speed: 3, acceleration: 2, max_speed: None },
+            BoatModel { start_speed: 0, acceleration: 1, max_speed: Some(4) },
+            BoatModel { start_speed: 5, acceleration: 3, max_speed: Some(20) },
+            BoatModel { start_speed: 2, acceleration: 0, max_speed: None },
+            BoatModel { start_speed: 0, acceleration: 0, max_speed: None },
+        ];
+
+        for boat in boats {
+            for time in 0..40 {
+                for record_distance in [0, 1, 9, 40, 100, 200, 500] {
+                    let race = race(time, record_distance);
+                    let winning: Vec<u64> = (0..=time)
+                        .filter(|x| boat.distance_traveled(*x, time) > record_distance as u128)
+                        .collect();
+                    let expected = winning.first().map(|first| *first..=*winning.last().unwrap());
+                    assert_eq!(boat.win_possible_interval(&race), expected, "{:?} in {:?}", boat, race);
+                }
+            }
+        }
+    }
+}
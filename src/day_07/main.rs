@@ -1,21 +1,28 @@
-use anyhow::{Result};
-use crate::parse::Input;
+use std::env;
+use std::fs::File;
+use std::io::BufWriter;
+use anyhow::{Context, Result};
+use crate::parse::{Hand, Input};
+use crate::rules::Rules;
 
 #[macro_use]
 extern crate simple_log;
 extern crate core;
 
+mod poker;
+
 mod parse {
     use std::cmp::Ordering;
     use std::fmt;
     use std::fmt::Formatter;
-    use aoc_parse::{parser, prelude::*};
-    use anyhow::{Result, Context};
+    use std::str::FromStr;
+    use anyhow::{bail, ensure, Error, Result, Context};
     use std::fs::read_to_string;
     use itertools::Itertools;
     use enum_ordinalize::Ordinalize;
+    use crate::rules::Rules;
 
-    #[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Ordinalize, Copy, Clone)]
+    #[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash, Ordinalize, Copy, Clone)]
     pub enum Card {
         Joker,
         Two,
@@ -44,105 +51,125 @@ mod parse {
         FiveOfAKind,
     }
 
+    impl Card {
+        pub fn from_label(label: char) -> Option<Card> {
+            match label {
+                'A' => Some(Card::Ass),
+                'K' => Some(Card::King),
+                'Q' => Some(Card::Queen),
+                'J' => Some(Card::Jack),
+                'T' => Some(Card::Ten),
+                '9' => Some(Card::Nine),
+                '8' => Some(Card::Eight),
+                '7' => Some(Card::Seven),
+                '6' => Some(Card::Six),
+                '5' => Some(Card::Five),
+                '4' => Some(Card::Four),
+                '3' => Some(Card::Three),
+                '2' => Some(Card::Two),
+                '*' => Some(Card::Joker),
+                _ => None
+            }
+        }
+    }
+
     #[derive(Debug)]
     pub struct Hand {
-        pub cards: [Card; 5],
+        pub cards: Vec<Card>,
         pub hand_type: HandType,
         pub type_with_joker: Option<HandType>,
-        pub bid: u32
+        pub bid: u32,
+        /// Position of the hand type and the cards in the order of the rules the hand was
+        /// created with, compared in this order
+        strength: (usize, Vec<usize>),
     }
 
-    pub fn type_with_joker(hand_type: &HandType, cards: &Vec<Card>) -> HandType {
-        let joker_count: u8 = cards.iter()
-            .filter_map(|c| match c {
-                Card::Joker => Some(1),
-                _ => None
-            })
-            .sum();
-
-        match hand_type {
-            HandType::FourOfAKind  => match joker_count {
-                4 => HandType::FiveOfAKind, // must be 4 jokers, so other card makes it 5
-                1 => HandType::FiveOfAKind,
-                0 => hand_type.clone(),
-                _ => panic!("unexpected joker count {} in {:?}", joker_count, cards)
-            }
-            HandType::ThreeOfAKind => {
-                match joker_count {
-                    // the three cards must be jokers already, the other two cards must be different
-                    // otherwise we had FullHouse
-                    3 => HandType::FourOfAKind,
-                    2 => HandType::FiveOfAKind,
-                    1 => HandType::FourOfAKind,
-                    0 => hand_type.clone(),
-                    _ => panic!("unexpected joker count {} in {:?}", joker_count, cards)
-                }
-            }
-            HandType::TwoPairs => match joker_count {
-                2 => HandType::FourOfAKind,
-                1 => HandType::FullHouse,
-                0 => hand_type.clone(),
-                _ => panic!("unexpected joker count {} in {:?}", joker_count, cards)
-            },
-            HandType::OnePair => match joker_count {
-                3 => HandType::FiveOfAKind,
-                2 => HandType::ThreeOfAKind, // the two cards must be the joker, otherwise we had TwoPair
-                1 => HandType::ThreeOfAKind,
-                0 => hand_type.clone(),
-                _ => panic!("unexpected joker count {} in {:?}", joker_count, cards)
-            },
-            HandType::HighCard => match joker_count {
-                4 => HandType::FiveOfAKind,
-                3 => HandType::FourOfAKind,
-                2 => HandType::ThreeOfAKind,
-                1 => HandType::OnePair,
-                0 => hand_type.clone(),
-                _ => panic!("unexpected joker count {} in {:?}", joker_count, cards)
+    impl HandType {
+        /// Classifies a hand by the sizes of its two largest groups of equal cards. Hands with
+        /// more than five cards can contain larger groups, those count as the best type they
+        /// contain.
+        pub fn from_groups(longest: usize, second_longest: usize) -> HandType {
+            match (longest, second_longest) {
+                (5.., _) => HandType::FiveOfAKind,
+                (4, _) => HandType::FourOfAKind,
+                (3, 2..) => HandType::FullHouse,
+                (3, _) => HandType::ThreeOfAKind,
+                (2, 2..) => HandType::TwoPairs,
+                (2, _) => HandType::OnePair,
+                (_, _) => HandType::HighCard,
             }
-            HandType::FullHouse  => match joker_count {
-                3 => HandType::FiveOfAKind,
-                2 => HandType::FiveOfAKind,
-                0 => hand_type.clone(),
-                _ => panic!("unexpected joker count {} in {:?}", joker_count, cards)
-            },
-            HandType::FiveOfAKind => hand_type.clone(), // hand is full, no way to improve it
         }
 
+        /// The best type the cards can form if every card for which `is_wild` holds can act
+        /// like any other card. Adding all wildcards to the most frequent regular card is always
+        /// the best choice, since it grows the largest group without shrinking any other.
+        pub fn best_of<F: Fn(&Card) -> bool>(cards: &[Card], is_wild: F) -> HandType {
+            let wildcards = cards.iter().filter(|c| is_wild(c)).count();
+            let counted = cards.iter()
+                .filter(|c| !is_wild(c))
+                .counts_by(|c| c.ordinal());
+            let mut groups = counted.values().sorted().rev();
+            let longest = groups.next().unwrap_or(&0) + wildcards;
+            let second_longest = groups.next().unwrap_or(&0);
+
+            HandType::from_groups(longest, *second_longest)
+        }
+    }
+
+    impl FromStr for Card {
+        type Err = Error;
+
+        fn from_str(s: &str) -> Result<Self> {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(label), None) => Card::from_label(label)
+                    .with_context(|| format!("invalid card '{}'", label)),
+                _ => bail!("expected a single card but got '{}'", s)
+            }
+        }
     }
 
     impl Hand {
-        pub fn new(cards: Vec<Card>, bid: u32, with_joker: bool) -> Self {
-            // get a copy of the cards as array for passing on
-            let cards_org_order: [Card; 5] = cards.clone().try_into()
-                .expect("infallible");
+        /// Reads a line of the puzzle input, i.e. cards optionally followed by the bid. A `J` is
+        /// read as joker if the rules have jokers.
+        pub fn parse(line: &str, rules: &Rules) -> Result<Self> {
+            let (labels, bid) = split_bid(line)?;
+            let cards = parse_cards(labels)
+                .with_context(|| format!("invalid hand '{}'", labels))?
+                .into_iter()
+                .map(|card| match card {
+                    Card::Jack if rules.has_jokers() => Card::Joker,
+                    card => card
+                })
+                .collect();
+
+            Hand::new(cards, bid, rules)
+        }
 
-            let counted = cards.iter().counts_by(|c| c.ordinal());
-            let mut groups = counted.values().sorted().rev();
-            let longest = groups.next().expect("no longest");
-            let second_longest = groups.next().unwrap_or(&0);
+        pub fn new(cards: Vec<Card>, bid: u32, rules: &Rules) -> Result<Self> {
+            ensure!(cards.len() == rules.hand_size, "hand {:?} has {} cards, expected {}",
+                cards, cards.len(), rules.hand_size);
 
-            let hand_type = match (longest, second_longest) {
-                (5, 0) => HandType::FiveOfAKind,
-                (4, 1) => HandType::FourOfAKind,
-                (3, 2) => HandType::FullHouse,
-                (3, 1) => HandType::ThreeOfAKind,
-                (2, 2) => HandType::TwoPairs,
-                (2, 1) => HandType::OnePair,
-                (1, _) => HandType::HighCard,
-                (_ ,_) => panic!("Unexpected pair counts: {}/{}", longest, second_longest)
-            };
-            let type_with_joker = if with_joker {
-                Some(type_with_joker(&hand_type, &cards))
-            } else {
+            let hand_type = HandType::best_of(&cards, |_| false);
+            let type_with_joker = if rules.wildcards.is_empty() {
                 None
+            } else {
+                Some(HandType::best_of(&cards, |c| rules.wildcards.contains(c)))
             };
 
-            Hand {
-                cards: cards_org_order,
+            let type_strength = rules.type_strength(type_with_joker.as_ref().unwrap_or(&hand_type));
+            let card_strengths = rules.comparison_order(&cards).into_iter()
+                .map(|idx| rules.card_strength(&cards[idx])
+                    .with_context(|| format!("card {:?} is not part of the game", cards[idx])))
+                .collect::<Result<Vec<usize>>>()?;
+
+            Ok(Hand {
+                cards,
                 hand_type,
                 type_with_joker,
-                bid
-            }
+                bid,
+                strength: (type_strength, card_strengths)
+            })
         }
 
     }
@@ -156,55 +183,77 @@ mod parse {
 
     impl PartialOrd for Hand {
         fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-            let by_type = if let Some(with_joker) = &self.type_with_joker {
-                let other_with_joker = other.type_with_joker.as_ref()
-                    .expect("other to have joker type");
-                with_joker.cmp(other_with_joker)
-            } else {
-                self.hand_type.cmp(&other.hand_type)
-            };
+            Some(self.cmp(other))
+        }
+    }
 
+    /// Only meaningful between hands created with the same rules.
+    impl Ord for Hand {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.strength.cmp(&other.strength)
+        }
+    }
 
-            match by_type {
-                Ordering::Less => Some(by_type),
-                Ordering::Greater => Some(by_type),
-                Ordering::Equal => {
-                    for (c_self, c_other) in self.cards.iter().zip(other.cards.iter()) {
-                        if c_self != c_other {
-                            return c_self.partial_cmp(c_other);
-                        }
-                    }
-                    Some(Ordering::Equal)
-                }
+    impl fmt::Display for Card {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            match self {
+                Card::Ass => write!(f, "A"),
+                Card::King => write!(f, "K"),
+                Card::Queen => write!(f, "Q"),
+                Card::Jack => write!(f, "J"),
+                Card::Ten => write!(f, "T"),
+                Card::Nine => write!(f, "9"),
+                Card::Eight => write!(f, "8"),
+                Card::Seven => write!(f, "7"),
+                Card::Six => write!(f, "6"),
+                Card::Five => write!(f, "5"),
+                Card::Four => write!(f, "4"),
+                Card::Three => write!(f, "3"),
+                Card::Two => write!(f, "2"),
+                Card::Joker => write!(f, "*"),
             }
         }
     }
 
-    impl Ord for Hand {
-        fn cmp(&self, other: &Self) -> Ordering {
-           self.partial_cmp(other).unwrap()
+    /// Reads a hand the way it is displayed, so it round-trips with `Display`: a `J` is a jack
+    /// and a `*` a joker. As the text doesn't say which rules apply, hands with jokers follow
+    /// the rules of part 2 and all others the rules of part 1. Only hands read under the same
+    /// rules can be compared, use [Hand::parse] to pick the rules.
+    impl FromStr for Hand {
+        type Err = Error;
+
+        fn from_str(s: &str) -> Result<Self> {
+            let (labels, bid) = split_bid(s)?;
+            let cards = parse_cards(labels)
+                .with_context(|| format!("invalid hand '{}'", labels))?;
+            let rules = if cards.contains(&Card::Joker) {
+                Rules::part_2()
+            } else {
+                Rules::part_1()
+            };
+            Hand::new(cards, bid, &rules)
         }
     }
 
+    fn split_bid(line: &str) -> Result<(&str, u32)> {
+        match line.split_once(' ') {
+            Some((labels, bid)) => Ok((labels, bid.parse().with_context(|| format!("invalid bid '{}'", bid))?)),
+            None => Ok((line, 0))
+        }
+    }
+
+    /// Reads card labels as displayed, a `J` is a jack and a `*` a joker. Whitespace is ignored.
+    pub fn parse_cards(labels: &str) -> Result<Vec<Card>> {
+        labels.chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| Card::from_label(c).with_context(|| format!("invalid card '{}'", c)))
+            .collect()
+    }
+
     impl fmt::Display for Hand {
         fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-            for card in self.cards {
-                match card {
-                    Card::Ass => write!(f, "A"),
-                    Card::King => write!(f, "K"),
-                    Card::Queen => write!(f, "Q"),
-                    Card::Jack => write!(f, "J"),
-                    Card::Ten => write!(f, "T"),
-                    Card::Nine => write!(f, "9"),
-                    Card::Eight => write!(f, "8"),
-                    Card::Seven => write!(f, "7"),
-                    Card::Six => write!(f, "6"),
-                    Card::Five => write!(f, "5"),
-                    Card::Four => write!(f, "4"),
-                    Card::Three => write!(f, "3"),
-                    Card::Two => write!(f, "2"),
-                    Card::Joker => write!(f, "*"),
-                }?;
+            for card in self.cards.iter() {
+                write!(f, "{}", card)?;
             }
             Ok(())
         }
@@ -216,89 +265,352 @@ mod parse {
         pub hands: Vec<Hand>
     }
 
-    pub(crate) fn parse_hand(hand: &str, with_joker: bool) -> Hand {
-        let parser = parser!(cards:{
-            "A" => Card::Ass,
-            "K" => Card::King,
-            "Q" => Card::Queen,
-            "J" => if with_joker {
-                Card::Joker
-            } else {
-                Card::Jack
-            },
-            "T" => Card::Ten,
-            "9" => Card::Nine,
-            "8" => Card::Eight,
-            "7" => Card::Seven,
-            "6" => Card::Six,
-            "5" => Card::Five,
-            "4" => Card::Four,
-            "3" => Card::Three,
-            "2" => Card::Two
-        }+);
-
-        Hand::new(parser.parse(hand).expect("valid hand"), 0, with_joker)
-    }
-
-
-    pub fn parse_input(filename: &str, with_joker: bool) -> Result<Input> {
-        let parser = parser!(lines(cards:{
-            "A" => Card::Ass,
-            "K" => Card::King,
-            "Q" => Card::Queen,
-            "J" => if with_joker {
-                Card::Joker
-            } else {
-                Card::Jack
-            },
-            "T" => Card::Ten,
-            "9" => Card::Nine,
-            "8" => Card::Eight,
-            "7" => Card::Seven,
-            "6" => Card::Six,
-            "5" => Card::Five,
-            "4" => Card::Four,
-            "3" => Card::Three,
-            "2" => Card::Two
-        }+ " " bid:u32 => Hand::new(cards, bid, with_joker)));
-
+    pub fn parse_input(filename: &str, rules: &Rules) -> Result<Input> {
         let raw_data = read_to_string(filename)?;
-        let hands = parser.parse(&raw_data).context("parse error")?;
+        let hands = raw_data.lines()
+            .enumerate()
+            .map(|(idx, line)| Hand::parse(line, rules)
+                .with_context(|| format!("invalid hand on line {}", idx + 1)))
+            .collect::<Result<Vec<Hand>>>()?;
 
         Ok(Input { hands })
     }
 }
 
+mod rules {
+    use std::cmp::Reverse;
+    use std::fs::read_to_string;
+    use anyhow::{bail, ensure, Context, Result};
+    use enum_ordinalize::Ordinalize;
+    use itertools::Itertools;
+    use crate::parse::{parse_cards, Card, HandType};
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    pub enum TieBreaker {
+        /// Compare the cards in the order they were dealt, the first differing card decides
+        FirstDifference,
+        /// Compare the strongest cards first, then the second strongest and so on
+        HighestCards,
+    }
+
+    /// Everything that makes up a variant of Camel Cards.
+    #[derive(Debug, Clone)]
+    pub struct Rules {
+        /// All cards of the game, from weakest to strongest
+        pub rank_order: Vec<Card>,
+        /// Cards that can act like any other card when determining the hand type
+        pub wildcards: Vec<Card>,
+        pub hand_size: usize,
+        /// All hand types, from weakest to strongest
+        pub type_order: Vec<HandType>,
+        pub tie_breaker: TieBreaker,
+    }
+
+    impl Rules {
+        pub fn part_1() -> Self {
+            Rules {
+                rank_order: Card::VARIANTS.iter()
+                    .filter(|c| c != &&Card::Joker)
+                    .copied()
+                    .collect(),
+                wildcards: vec![],
+                hand_size: 5,
+                type_order: HandType::VARIANTS.to_vec(),
+                tie_breaker: TieBreaker::FirstDifference,
+            }
+        }
+
+        /// J cards are now jokers - wildcards that can act like whatever card would make the
+        /// hand the strongest type possible, but are the weakest individual cards.
+        pub fn part_2() -> Self {
+            Rules {
+                rank_order: Card::VARIANTS.iter()
+                    .filter(|c| c != &&Card::Jack)
+                    .copied()
+                    .collect(),
+                wildcards: vec![Card::Joker],
+                ..Rules::part_1()
+            }
+        }
+
+        /// Jokers replace the jacks, so `J` in a hand has to be read as a joker.
+        pub fn has_jokers(&self) -> bool {
+            self.wildcards.contains(&Card::Joker)
+        }
+
+        pub fn card_strength(&self, card: &Card) -> Option<usize> {
+            self.rank_order.iter().position(|c| c == card)
+        }
+
+        /// Indices of the cards in the order they are compared when breaking a tie.
+        pub fn comparison_order(&self, cards: &[Card]) -> Vec<usize> {
+            match self.tie_breaker {
+                TieBreaker::FirstDifference => (0..cards.len()).collect(),
+                TieBreaker::HighestCards => (0..cards.len())
+                    .sorted_by_key(|idx| Reverse(self.card_strength(&cards[*idx])))
+                    .collect()
+            }
+        }
+
+        pub fn type_strength(&self, hand_type: &HandType) -> usize {
+            self.type_order.iter().position(|t| t == hand_type)
+                .expect("all hand types are ordered")
+        }
+
+        /// Reads rules from a file of `key: value` lines, any rule not given is taken from
+        /// part 1. For example part 2 would be:
+        ///
+        /// ```text
+        /// # jacks are jokers
+        /// ranks: J 2 3 4 5 6 7 8 9 T Q K A
+        /// wild: J
+        /// hand size: 5
+        /// types: HighCard OnePair TwoPairs ThreeOfAKind FullHouse FourOfAKind FiveOfAKind
+        /// tie breaker: first difference
+        /// ```
+        ///
+        /// A wild `J` is a joker. Without a `ranks` line it is also the weakest card like in
+        /// part 2, otherwise it keeps its place in `ranks`.
+        pub fn from_file(filename: &str) -> Result<Self> {
+            let mut rules = Rules::part_1();
+            let mut ranks_given = false;
+
+            for line in read_to_string(filename)?.lines() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue
+                }
+                let (key, value) = line.split_once(':')
+                    .with_context(|| format!("expected 'key: value' but got '{}'", line))?;
+                let value = value.trim();
+
+                match key.trim() {
+                    "ranks" => {
+                        rules.rank_order = parse_cards(value)?;
+                        ranks_given = true;
+                    }
+                    "wild" => rules.wildcards = parse_cards(value)?,
+                    "hand size" => rules.hand_size = value.parse()
+                        .with_context(|| format!("invalid hand size '{}'", value))?,
+                    "types" => rules.type_order = value.split_whitespace()
+                        .map(|name| HandType::VARIANTS.iter()
+                            .find(|t| format!("{:?}", t) == name)
+                            .cloned()
+                            .with_context(|| format!("unknown hand type '{}'", name)))
+                        .collect::<Result<Vec<HandType>>>()?,
+                    "tie breaker" => rules.tie_breaker = match value {
+                        "first difference" => TieBreaker::FirstDifference,
+                        "highest cards" => TieBreaker::HighestCards,
+                        other => bail!("unknown tie breaker '{}'", other)
+                    },
+                    other => bail!("unknown rule '{}'", other)
+                }
+            }
+
+            if rules.wildcards.contains(&Card::Jack) {
+                for card in rules.rank_order.iter_mut().chain(rules.wildcards.iter_mut()) {
+                    if card == &Card::Jack {
+                        *card = Card::Joker;
+                    }
+                }
+                if !ranks_given {
+                    rules.rank_order.retain(|card| card != &Card::Joker);
+                    rules.rank_order.insert(0, Card::Joker);
+                }
+            }
+
+            ensure!(HandType::VARIANTS.iter().all(|t| rules.type_order.contains(t))
+                && rules.type_order.len() == HandType::VARIANT_COUNT,
+                "types must list every hand type exactly once");
+            ensure!(rules.wildcards.iter().all(|c| rules.rank_order.contains(c)),
+                "wildcards must be part of the ranks");
+
+            Ok(rules)
+        }
+    }
+}
+
+mod explain {
+    use std::cmp::Ordering;
+    use std::fmt;
+    use std::fmt::Formatter;
+    use std::io::Write;
+    use anyhow::Result;
+    use crate::parse::{Card, Hand, HandType};
+    use crate::rules::{Rules, TieBreaker};
+
+    #[derive(Debug, PartialEq)]
+    pub enum Reason {
+        /// The hands have different types
+        HandType(HandType, HandType),
+        /// Same type, but the cards differ at the given position in the order they are compared
+        Card { position: usize, tie_breaker: TieBreaker, cards: (Card, Card) },
+        /// Same type and equally strong cards everywhere
+        Identical,
+    }
+
+    #[derive(Debug)]
+    pub struct Explanation<'a> {
+        pub hand: &'a Hand,
+        pub other: &'a Hand,
+        pub ordering: Ordering,
+        pub reason: Reason,
+    }
+
+    /// Works out why `hand` ranks higher, lower or equal to `other`.
+    pub fn explain<'a>(hand: &'a Hand, other: &'a Hand, rules: &Rules) -> Explanation<'a> {
+        let hand_type = effective_type(hand);
+        let other_type = effective_type(other);
+
+        let reason = if hand_type != other_type {
+            Reason::HandType(hand_type.clone(), other_type.clone())
+        } else {
+            let hand_order = rules.comparison_order(&hand.cards);
+            let other_order = rules.comparison_order(&other.cards);
+            hand_order.into_iter().zip(other_order)
+                .map(|(idx, other_idx)| (hand.cards[idx], other.cards[other_idx]))
+                .enumerate()
+                .find(|(_, (card, other_card))| rules.card_strength(card) != rules.card_strength(other_card))
+                .map(|(position, cards)| Reason::Card { position, tie_breaker: rules.tie_breaker, cards })
+                .unwrap_or(Reason::Identical)
+        };
+
+        Explanation {
+            hand,
+            other,
+            ordering: hand.cmp(other),
+            reason,
+        }
+    }
+
+    fn effective_type(hand: &Hand) -> &HandType {
+        hand.type_with_joker.as_ref().unwrap_or(&hand.hand_type)
+    }
+
+    impl fmt::Display for Explanation<'_> {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            match self.ordering {
+                Ordering::Greater => write!(f, "{} beats {}", self.hand, self.other),
+                Ordering::Less => write!(f, "{} loses to {}", self.hand, self.other),
+                Ordering::Equal => write!(f, "{} ties with {}", self.hand, self.other),
+            }?;
+            match &self.reason {
+                Reason::HandType(hand_type, other_type) =>
+                    write!(f, ": {:?} against {:?}", hand_type, other_type),
+                Reason::Card { position, tie_breaker: TieBreaker::FirstDifference, cards: (card, other_card) } =>
+                    write!(f, ": both are {:?}, card {} is {} against {}",
+                           effective_type(self.hand), position + 1, card, other_card),
+                Reason::Card { position, tie_breaker: TieBreaker::HighestCards, cards: (card, other_card) } =>
+                    write!(f, ": both are {:?}, highest card {} is {} against {}",
+                           effective_type(self.hand), position + 1, card, other_card),
+                Reason::Identical =>
+                    write!(f, ": both are {:?} with equally strong cards", effective_type(self.hand)),
+            }
+        }
+    }
+
+    fn type_name(hand_type: &Option<HandType>) -> String {
+        hand_type.as_ref().map(|t| format!("{:?}", t)).unwrap_or_default()
+    }
+
+    /// Writes the hands, which must already be sorted from weakest to strongest, as CSV table.
+    pub fn write_csv<W: Write>(hands: &[Hand], out: &mut W) -> Result<()> {
+        writeln!(out, "rank,hand,type,joker_type,bid,winnings")?;
+        for (idx, hand) in hands.iter().enumerate() {
+            writeln!(out, "{},{},{:?},{},{},{}", idx + 1, hand, hand.hand_type,
+                     type_name(&hand.type_with_joker), hand.bid, hand.bid as u64 * (idx as u64 + 1))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the hands, which must already be sorted from weakest to strongest, as JSON array.
+    pub fn write_json<W: Write>(hands: &[Hand], out: &mut W) -> Result<()> {
+        writeln!(out, "[")?;
+        for (idx, hand) in hands.iter().enumerate() {
+            let joker_type = match &hand.type_with_joker {
+                Some(_) => format!("\"{}\"", type_name(&hand.type_with_joker)),
+                None => "null".to_string()
+            };
+            let separator = if idx + 1 < hands.len() { "," } else { "" };
+            writeln!(out, "  {{\"rank\": {}, \"hand\": \"{}\", \"type\": \"{:?}\", \"joker_type\": {}, \"bid\": {}, \"winnings\": {}}}{}",
+                     idx + 1, hand, hand.hand_type, joker_type, hand.bid, hand.bid as u64 * (idx as u64 + 1), separator)?;
+        }
+        writeln!(out, "]")?;
+        Ok(())
+    }
+}
+
 fn solve_common(mut input: Input) -> Result<u32> {
     input.hands.sort();
 
     let mut total_winnings = 0_u32;
     for (idx, hand) in input.hands.iter().enumerate() {
-        if let Some(with_joker_type) = &hand.type_with_joker {
-            println!("{:?} {} is rank {} ", with_joker_type, hand, &idx + 1);
-        } else {
-            println!("{} is rank {} ", hand, &idx + 1);
-        }
-        total_winnings += &hand.bid * (idx as u32 + 1);
+        debug!("{} is rank {}", hand, idx + 1);
+        total_winnings += hand.bid * (idx as u32 + 1);
     }
 
     Ok(total_winnings)
 }
 
 fn solve_part_1(filename: &str) -> Result<u32> {
-    let input = parse::parse_input(filename, false)?;
+    let input = parse::parse_input(filename, &Rules::part_1())?;
     solve_common(input)
 }
 
 fn solve_part_2(filename: &str) -> Result<u32> {
-    let input = parse::parse_input(filename, true)?;
+    let input = parse::parse_input(filename, &Rules::part_2())?;
 
     solve_common(input)
 }
 
+fn solve_with_rules(filename: &str, rules: &Rules) -> Result<u32> {
+    let input = parse::parse_input(filename, rules)?;
+    solve_common(input)
+}
+
 fn main() -> Result<()> {
     simple_log::quick!("info");
 
+    let args: Vec<String> = env::args().collect();
+    let rules_from_args = |idx: usize| args.get(idx)
+        .map(|filename| Rules::from_file(filename))
+        .unwrap_or(Ok(Rules::part_2()));
+
+    if let Some("poker") = args.get(1).map(String::as_str) {
+        let filename = args.get(2).context("usage: day_07 poker <input>")?;
+        info!("Result poker: {}", poker::solve(filename)?);
+        return Ok(())
+    }
+    if let Some("explain") = args.get(1).map(String::as_str) {
+        let rules = rules_from_args(4)?;
+        let hand = Hand::parse(args.get(2).context("usage: day_07 explain <hand> <hand> [rules file]")?, &rules)?;
+        let other = Hand::parse(args.get(3).context("missing second hand")?, &rules)?;
+        info!("{}", explain::explain(&hand, &other, &rules));
+        return Ok(())
+    }
+    if let Some("table") = args.get(1).map(String::as_str) {
+        let table_filename = args.get(2).context("usage: day_07 table <csv or json file> [rules file] [input]")?;
+        let rules = rules_from_args(3)?;
+        let filename = args.get(4).map(String::as_str).unwrap_or("src/day_07/input.txt");
+        let mut input = parse::parse_input(filename, &rules)?;
+        input.hands.sort();
+
+        let mut out = BufWriter::new(File::create(table_filename)?);
+        if table_filename.ends_with(".json") {
+            explain::write_json(&input.hands, &mut out)?;
+        } else {
+            explain::write_csv(&input.hands, &mut out)?;
+        }
+        info!("Wrote {} ranked hands to {}", input.hands.len(), table_filename);
+        return Ok(())
+    }
+    if let Some(rules_filename) = args.get(1) {
+        let rules = Rules::from_file(rules_filename)?;
+        let filename = args.get(2).map(String::as_str).unwrap_or("src/day_07/input.txt");
+        info!("Result with rules from {}: {}", rules_filename, solve_with_rules(filename, &rules)?);
+        return Ok(())
+    }
+
     info!("Result part 1: {}", solve_part_1("src/day_07/input.txt")?);
     info!("Result part 2: {}", solve_part_2("src/day_07/input.txt")?);
     Ok(())
@@ -307,8 +619,15 @@ fn main() -> Result<()> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{solve_part_1, solve_part_2};
-    use crate::parse::{HandType, parse_hand, parse_input};
+    use std::cmp::Ordering;
+    use crate::{explain, solve_part_1, solve_part_2, solve_with_rules};
+    use enum_ordinalize::Ordinalize;
+    use crate::parse::{Card, Hand, HandType, parse_input};
+    use crate::rules::{Rules, TieBreaker};
+
+    fn parse_hand(cards: &str, rules: &Rules) -> Hand {
+        Hand::parse(cards, rules).unwrap()
+    }
 
     #[test]
     fn solve_test_input_1() {
@@ -325,7 +644,7 @@ mod tests {
 
     #[test]
     fn test_hand_type_part_1() {
-        let input = parse_input("src/day_07/test_input.txt", false).unwrap();
+        let input = parse_input("src/day_07/test_input.txt", &Rules::part_1()).unwrap();
 
         assert_eq!(input.hands[0].hand_type, HandType::OnePair);
         assert_eq!(input.hands[1].hand_type, HandType::ThreeOfAKind);
@@ -336,15 +655,40 @@ mod tests {
 
     #[test]
     fn test_tie_breaking_part_1() {
-        assert!(parse_hand("AAAAQ", false) > parse_hand("AAAAJ", false));
-        assert!(parse_hand("AAAQQ", false) > parse_hand("AAAJJ", false));
-        assert!(parse_hand("264AJ", false) < parse_hand("269J8", false))
+        assert!(parse_hand("AAAAQ", &Rules::part_1()) > parse_hand("AAAAJ", &Rules::part_1()));
+        assert!(parse_hand("AAAQQ", &Rules::part_1()) > parse_hand("AAAJJ", &Rules::part_1()));
+        assert!(parse_hand("264AJ", &Rules::part_1()) < parse_hand("269J8", &Rules::part_1()))
     }
 
     #[test]
     fn hand_ordering_part_1() {
-        assert!(parse_hand("T55J5", false) > parse_hand("KTJJT", false));
-        assert!(parse_hand("KK677", false) > parse_hand("KTJJT", false));
+        assert!(parse_hand("T55J5", &Rules::part_1()) > parse_hand("KTJJT", &Rules::part_1()));
+        assert!(parse_hand("KK677", &Rules::part_1()) > parse_hand("KTJJT", &Rules::part_1()));
+    }
+
+    #[test]
+    fn solve_with_rules_from_file() {
+        let rules = Rules::from_file("src/day_07/rules_jokers.txt").unwrap();
+        assert_eq!(rules.rank_order, Rules::part_2().rank_order);
+        assert_eq!(rules.wildcards, vec![Card::Joker]);
+        assert_eq!(solve_with_rules("src/day_07/test_input.txt", &rules).unwrap(), 5905);
+
+        let rules = Rules::from_file("src/day_07/rules_wild_jacks.txt").unwrap();
+        assert_eq!(rules.rank_order, Rules::part_2().rank_order);
+        assert_eq!(solve_with_rules("src/day_07/test_input.txt", &rules).unwrap(), 5905);
+
+        let rules = Rules::from_file("src/day_07/rules_seven_cards.txt").unwrap();
+        assert_eq!(rules.wildcards, vec![Card::Joker, Card::Two]);
+        assert_eq!(rules.hand_size, 7);
+        assert_eq!(solve_with_rules("src/day_07/test_input_seven_cards.txt", &rules).unwrap(), 420);
+        assert!(solve_with_rules("src/day_07/test_input.txt", &rules).is_err());
+    }
+
+    #[test]
+    fn tie_breaking_by_highest_cards() {
+        let rules = Rules { tie_breaker: TieBreaker::HighestCards, ..Rules::part_1() };
+        assert!(parse_hand("264AJ", &rules) > parse_hand("269J8", &rules));
+        assert_eq!(parse_hand("AAKK2", &rules).cmp(&parse_hand("2KAKA", &rules)), Ordering::Equal);
     }
 
     #[test]
@@ -358,9 +702,147 @@ mod tests {
         ];
 
         for (cards, expected_type) in test_cases {
-            let hand = parse_hand(cards, true);
+            let hand = parse_hand(cards, &Rules::part_2());
             assert_eq!(hand.type_with_joker.clone().unwrap(), expected_type,
                        "{} has type {:?}, expected {:?}", hand, hand.type_with_joker, expected_type);
         }
     }
-}
\ No newline at end of file
+
+    /// All distinct hands of the given size, ignoring the order of the cards.
+    fn all_hands(size: usize, cards: &[Card]) -> Vec<Vec<Card>> {
+        if size == 0 {
+            return vec![vec![]];
+        }
+        let mut hands = vec![];
+        for (idx, card) in cards.iter().enumerate() {
+            for mut rest in all_hands(size - 1, &cards[idx..]) {
+                rest.push(*card);
+                hands.push(rest);
+            }
+        }
+        hands
+    }
+
+    /// Tries every possible card for every joker and returns the best resulting type. As the
+    /// order of the cards does not matter for the type, each joker is only replaced with cards
+    /// that are not lower than the replacement for the previous joker.
+    fn best_type_by_substitution(cards: &[Card], lowest_substitute: usize) -> HandType {
+        match cards.iter().position(|c| c == &Card::Joker) {
+            None => HandType::best_of(cards, |_| false),
+            Some(joker_idx) => Card::VARIANTS.iter()
+                .enumerate()
+                .skip(lowest_substitute)
+                .filter(|(_, c)| c != &&Card::Joker)
+                .map(|(substitute_idx, substitute)| {
+                    let mut substituted = cards.to_vec();
+                    substituted[joker_idx] = *substitute;
+                    best_type_by_substitution(&substituted, substitute_idx)
+                })
+                .max()
+                .unwrap()
+        }
+    }
+
+    #[test]
+    fn best_type_matches_substitution() {
+        let non_jacks: Vec<Card> = Card::VARIANTS.iter()
+            .filter(|c| c != &&Card::Jack)
+            .copied()
+            .collect();
+
+        for size in 1..=6 {
+            for hand in all_hands(size, &non_jacks) {
+                assert_eq!(HandType::best_of(&hand, |c| c == &Card::Joker), best_type_by_substitution(&hand, 0),
+                           "best type of {:?}", hand);
+            }
+        }
+    }
+
+    #[test]
+    fn explain_ranking() {
+        let rules = Rules::part_1();
+        let explained = |hand: &str, other: &str| explain::explain(
+            &parse_hand(hand, &rules), &parse_hand(other, &rules), &rules).to_string();
+
+        assert_eq!(explained("T55J5", "KK677"), "T55J5 beats KK677: ThreeOfAKind against TwoPairs");
+        assert_eq!(explained("KTJJT", "KK677"), "KTJJT loses to KK677: both are TwoPairs, card 2 is T against K");
+        assert_eq!(explained("KK677", "KK677"), "KK677 ties with KK677: both are TwoPairs with equally strong cards");
+
+        let rules = Rules::part_2();
+        let (hand, other) = (parse_hand("JKKK2", &rules), parse_hand("QQQQ2", &rules));
+        let explanation = explain::explain(&hand, &other, &rules);
+        assert_eq!(explanation.reason, explain::Reason::Card {
+            position: 0, tie_breaker: TieBreaker::FirstDifference, cards: (Card::Joker, Card::Queen)
+        });
+        assert_eq!(explanation.to_string(), "*KKK2 loses to QQQQ2: both are FourOfAKind, card 1 is * against Q");
+    }
+
+    #[test]
+    fn ranked_table() {
+        let mut input = parse_input("src/day_07/test_input.txt", &Rules::part_2()).unwrap();
+        input.hands.sort();
+
+        let mut csv = vec![];
+        explain::write_csv(&input.hands, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().next(), Some("rank,hand,type,joker_type,bid,winnings"));
+        assert_eq!(csv.lines().nth(5), Some("5,KT**T,TwoPairs,FourOfAKind,220,1100"));
+
+        let mut json = vec![];
+        explain::write_json(&input.hands, &mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert_eq!(json.lines().nth(1), Some(r#"  {"rank": 1, "hand": "32T3K", "type": "OnePair", "joker_type": "OnePair", "bid": 765, "winnings": 765},"#));
+        assert_eq!(json.lines().last(), Some("]"));
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for card in Card::VARIANTS {
+            assert_eq!(&card.to_string().parse::<Card>().unwrap(), card);
+        }
+
+        for rules in [Rules::part_1(), Rules::part_2()] {
+            for hand in parse_input("src/day_07/test_input.txt", &rules).unwrap().hands {
+                let parsed: Hand = format!("{} {}", hand, hand.bid).parse().unwrap();
+                assert_eq!(parsed.to_string(), hand.to_string());
+                assert_eq!(parsed.cards, hand.cards);
+                assert_eq!(parsed.bid, hand.bid);
+
+                let parsed = Hand::parse(&format!("{} {}", hand, hand.bid), &rules).unwrap();
+                assert_eq!(parsed.to_string(), hand.to_string());
+                assert_eq!(parsed.bid, hand.bid);
+                assert_eq!(parsed, hand);
+                assert_eq!(parsed.cards, hand.cards);
+                assert_eq!(parsed.type_with_joker, hand.type_with_joker);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_hand_with_joker() {
+        let hand: Hand = "KT*QT 220".parse().unwrap();
+        assert_eq!(hand.cards, vec![Card::King, Card::Ten, Card::Joker, Card::Queen, Card::Ten]);
+        assert_eq!(hand.type_with_joker, Some(HandType::ThreeOfAKind));
+        assert_eq!(hand.to_string(), "KT*QT");
+        assert_eq!(hand.to_string().parse::<Hand>().unwrap(), hand);
+
+        // the joker picks the rules of part 2, which have no jacks, the jack picks part 1
+        assert_eq!(format!("{:#}", "KT*JT".parse::<Hand>().unwrap_err()), "card Jack is not part of the game");
+        assert_eq!("KTJQT".parse::<Hand>().unwrap().type_with_joker, None);
+    }
+
+    #[test]
+    fn invalid_hands() {
+        let error = |hand: &str| format!("{:#}", Hand::parse(hand, &Rules::part_1()).unwrap_err());
+
+        assert_eq!(error("KTXJT"), "invalid hand 'KTXJT': invalid card 'X'");
+        assert_eq!(error("KTJ"), "hand [King, Ten, Jack] has 3 cards, expected 5");
+        assert_eq!(error("KTJJTQ"), "hand [King, Ten, Jack, Jack, Ten, Queen] has 6 cards, expected 5");
+        assert_eq!(error("KT*JT"), "card Joker is not part of the game");
+        assert_eq!(error("KTJJT x"), "invalid bid 'x': invalid digit found in string");
+        assert!("".parse::<Card>().is_err());
+        assert!("AK".parse::<Card>().is_err());
+        assert!(parse_input("src/day_07/test_input.txt", &Rules::from_file("src/day_07/rules_seven_cards.txt").unwrap())
+            .unwrap_err().to_string().contains("line 1"));
+    }
+}
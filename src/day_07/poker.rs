@@ -0,0 +1,278 @@
+use std::cmp::{Ordering, Reverse};
+use std::fmt;
+use std::fmt::Formatter;
+use std::fs::read_to_string;
+use aoc_parse::{parser, prelude::*};
+use anyhow::{bail, ensure, Context, Result};
+use enum_ordinalize::Ordinalize;
+use itertools::Itertools;
+use crate::parse::Card;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct PokerCard {
+    pub card: Card,
+    pub suit: Suit,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum PokerHandType {
+    HighCard,
+    OnePair,
+    TwoPairs,
+    ThreeOfAKind,
+    Straight,
+    Flush,
+    FullHouse,
+    FourOfAKind,
+    StraightFlush,
+}
+
+#[derive(Debug)]
+pub struct PokerHand {
+    /// The best five cards out of all cards dealt
+    pub cards: Vec<PokerCard>,
+    pub hand_type: PokerHandType,
+    /// Cards deciding between hands of the same type, most important first
+    pub kickers: Vec<Card>,
+    pub bid: u32,
+}
+
+impl PokerHand {
+    /// Picks the best five cards out of the given ones.
+    pub fn new(cards: Vec<PokerCard>, bid: u32) -> Result<Self> {
+        ensure!(cards.len() >= 5, "a poker hand needs at least 5 cards, got {}", cards.len());
+        ensure!(cards.iter().all(|c| c.card != Card::Joker), "jokers are not part of poker");
+        ensure!(cards.iter().all_unique(), "a poker hand can't hold the same card twice, got {}",
+            cards.iter().join(""));
+
+        let (cards, hand_type, kickers) = cards.into_iter()
+            .combinations(5)
+            .map(|five| {
+                let (hand_type, kickers) = evaluate(&five);
+                (five, hand_type, kickers)
+            })
+            .max_by(|(_, type_a, kickers_a), (_, type_b, kickers_b)| {
+                type_a.cmp(type_b).then_with(|| kickers_a.cmp(kickers_b))
+            })
+            .expect("at least one combination");
+
+        Ok(PokerHand { cards, hand_type, kickers, bid })
+    }
+}
+
+/// Determines the type of exactly five cards, plus the kickers to break ties. Aces can also
+/// be low in a straight (A2345), then the five is the highest card.
+fn evaluate(cards: &[PokerCard]) -> (PokerHandType, Vec<Card>) {
+    let is_flush = cards.iter().map(|c| c.suit).all_equal();
+
+    // distinct cards, ordered by how often they appear and then by strength
+    let groups: Vec<(usize, Card)> = cards.iter()
+        .counts_by(|c| c.card)
+        .into_iter()
+        .map(|(card, count)| (count, card))
+        .sorted_by_key(|group| Reverse(*group))
+        .collect();
+    let kickers: Vec<Card> = groups.iter().map(|(_, card)| *card).collect();
+
+    let straight_high = if groups.len() == 5 {
+        let highest = kickers[0].ordinal();
+        let lowest = kickers[4].ordinal();
+        if highest - lowest == 4 {
+            Some(kickers[0])
+        } else if kickers == [Card::Ass, Card::Five, Card::Four, Card::Three, Card::Two] {
+            Some(Card::Five)
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    let hand_type = match (straight_high, is_flush, groups[0].0, groups.get(1).map(|g| g.0)) {
+        (Some(_), true, _, _) => PokerHandType::StraightFlush,
+        (_, _, 4, _) => PokerHandType::FourOfAKind,
+        (_, _, 3, Some(2)) => PokerHandType::FullHouse,
+        (_, true, _, _) => PokerHandType::Flush,
+        (Some(_), _, _, _) => PokerHandType::Straight,
+        (_, _, 3, _) => PokerHandType::ThreeOfAKind,
+        (_, _, 2, Some(2)) => PokerHandType::TwoPairs,
+        (_, _, 2, _) => PokerHandType::OnePair,
+        (_, _, _, _) => PokerHandType::HighCard,
+    };
+
+    match straight_high {
+        Some(high) if hand_type == PokerHandType::Straight || hand_type == PokerHandType::StraightFlush =>
+            (hand_type, vec![high]),
+        _ => (hand_type, kickers)
+    }
+}
+
+impl PartialEq<Self> for PokerHand {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for PokerHand {}
+
+impl PartialOrd for PokerHand {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PokerHand {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.hand_type.cmp(&other.hand_type)
+            .then_with(|| self.kickers.cmp(&other.kickers))
+    }
+}
+
+impl Suit {
+    pub fn from_label(label: char) -> Option<Suit> {
+        match label {
+            'c' => Some(Suit::Clubs),
+            'd' => Some(Suit::Diamonds),
+            'h' => Some(Suit::Hearts),
+            's' => Some(Suit::Spades),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for Suit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Suit::Clubs => write!(f, "c"),
+            Suit::Diamonds => write!(f, "d"),
+            Suit::Hearts => write!(f, "h"),
+            Suit::Spades => write!(f, "s"),
+        }
+    }
+}
+
+impl fmt::Display for PokerCard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.card, self.suit)
+    }
+}
+
+impl fmt::Display for PokerHand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for card in self.cards.iter() {
+            write!(f, "{}", card)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads cards written as rank followed by suit, like `Ah` for the ace of hearts.
+pub fn parse_cards(cards: &str) -> Result<Vec<PokerCard>> {
+    cards.chars()
+        .chunks(2)
+        .into_iter()
+        .map(|mut label| {
+            let (Some(card), Some(suit)) = (label.next(), label.next()) else {
+                bail!("'{}' has a card without suit", cards)
+            };
+            Ok(PokerCard {
+                card: Card::from_label(card).with_context(|| format!("unknown card '{}'", card))?,
+                suit: Suit::from_label(suit).with_context(|| format!("unknown suit '{}'", suit))?,
+            })
+        })
+        .collect()
+}
+
+/// Same format as the camel cards input, but with a suit after every card, e.g. `AhKh7c7d2s 765`.
+pub fn parse_input(filename: &str) -> Result<Vec<PokerHand>> {
+    let parser = parser!(lines(string(alnum+) " " u32));
+
+    let raw_data = read_to_string(filename)?;
+    parser.parse(&raw_data).context("parse error")?
+        .into_iter()
+        .map(|(cards, bid)| PokerHand::new(parse_cards(&cards)?, bid))
+        .collect()
+}
+
+pub fn solve(filename: &str) -> Result<u64> {
+    let mut hands = parse_input(filename)?;
+    hands.sort();
+
+    Ok(hands.iter()
+        .enumerate()
+        .map(|(idx, hand)| hand.bid as u64 * (idx as u64 + 1))
+        .sum())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::Card;
+    use crate::poker::{parse_cards, solve, PokerHand, PokerHandType};
+
+    fn hand(cards: &str) -> PokerHand {
+        PokerHand::new(parse_cards(cards).unwrap(), 0).unwrap()
+    }
+
+    #[test]
+    fn solve_test_input() {
+        assert_eq!(solve("src/day_07/test_input_poker.txt").unwrap(), 810);
+    }
+
+    #[test]
+    fn poker_hand_types() {
+        let test_cases = vec![
+            ("Ah7c2d9sJh", PokerHandType::HighCard),
+            ("AhAc2d9sJh", PokerHandType::OnePair),
+            ("AhAc2d2sJh", PokerHandType::TwoPairs),
+            ("AhAcAd2sJh", PokerHandType::ThreeOfAKind),
+            ("9h8c7dTs6h", PokerHandType::Straight),
+            ("Ah2c3d4s5h", PokerHandType::Straight),
+            ("Ah7h2h9hJh", PokerHandType::Flush),
+            ("AhAcAd2s2h", PokerHandType::FullHouse),
+            ("AhAcAdAs2h", PokerHandType::FourOfAKind),
+            ("9h8h7hThJh", PokerHandType::StraightFlush),
+            ("Ac2c3c4c5c", PokerHandType::StraightFlush),
+        ];
+
+        for (cards, expected_type) in test_cases {
+            assert_eq!(hand(cards).hand_type, expected_type, "type of {}", cards);
+        }
+    }
+
+    #[test]
+    fn poker_hand_ordering() {
+        assert!(hand("Ah2c3d4s5h") < hand("2h3c4d5s6h"));
+        assert!(hand("AhAcKd9s2h") > hand("AdAsQd9s2h"));
+        assert!(hand("KhKcQdQs2h") > hand("KdKsJdJs9h"));
+        assert!(hand("KhKcQdQs3h") > hand("KdKsQhQc2h"));
+        assert!(hand("Ah7h2h9hJh") > hand("9h8c7dTs6h"));
+        assert_eq!(hand("AhKhQhJhTh"), hand("AsKsQsJsTs"));
+    }
+
+    #[test]
+    fn best_five_of_seven() {
+        let best = hand("Ah7h2h9hJh3c3d");
+        assert_eq!(best.hand_type, PokerHandType::Flush);
+        assert_eq!(best.to_string(), "Ah7h2h9hJh");
+
+        let best = hand("2h3h4h5h6h8cAd");
+        assert_eq!(best.hand_type, PokerHandType::StraightFlush);
+        assert_eq!(best.kickers, vec![Card::Six]);
+    }
+
+    #[test]
+    fn invalid_poker_hands() {
+        assert!(parse_cards("Ax").is_err());
+        assert!(parse_cards("AhK").is_err());
+        assert!(PokerHand::new(parse_cards("AhKh").unwrap(), 0).is_err());
+        assert!(PokerHand::new(parse_cards("AhAhAhAhAh").unwrap(), 0).is_err());
+        assert!(PokerHand::new(parse_cards("AhKhQhJhTh9cAh").unwrap(), 0).is_err());
+    }
+}
@@ -1,5 +1,8 @@
+use std::env;
 use std::iter::repeat;
+use advent_of_code_2023::common::flag_value;
 use anyhow::{Result};
+use crate::export::GhostPath;
 use crate::parse::{Input, Move};
 
 #[macro_use]
@@ -60,6 +63,201 @@ mod parse {
 }
 
 
+mod export {
+    use std::collections::{HashMap, HashSet};
+    use std::fmt::Write;
+    use anyhow::{Context, Result};
+    use crate::parse::{Input, Move};
+
+    /// Where a ghost walks from its start node: first the `lead_in`, then the `cycle` forever.
+    /// A ghost is back in a state it has been in once it reaches the same node at the same
+    /// position of the moves, so the cycle can be a lot longer than a loop in the network.
+    #[derive(Debug, PartialEq)]
+    pub struct GhostPath {
+        /// Nodes and the direction taken from them
+        pub lead_in: Vec<(String, char)>,
+        pub cycle: Vec<(String, char)>,
+    }
+
+    impl GhostPath {
+        pub fn follow(input: &Input, start: &str) -> Result<Self> {
+            let mut seen: HashMap<(&str, usize), usize> = HashMap::new();
+            let mut steps: Vec<(String, char)> = vec![];
+            let mut current = input.nodes.get_key_value(start)
+                .with_context(|| format!("there is no node {}", start))?.0.as_str();
+            let mut move_idx = 0;
+
+            while !input.moves.is_empty() {
+                if let Some(cycle_start) = seen.insert((current, move_idx), steps.len()) {
+                    let cycle = steps.split_off(cycle_start);
+                    return Ok(GhostPath { lead_in: steps, cycle });
+                }
+                let node = input.nodes.get(current).with_context(|| format!("there is no node {}", current))?;
+                let (direction, next) = match input.moves[move_idx] {
+                    Move::Left => ('L', &node.left),
+                    Move::Right => ('R', &node.right),
+                };
+                steps.push((current.to_string(), direction));
+                current = next;
+                move_idx = (move_idx + 1) % input.moves.len();
+            }
+            Ok(GhostPath { lead_in: vec![], cycle: vec![] })
+        }
+
+        fn edges(steps: &[(String, char)]) -> HashSet<(&str, char)> {
+            steps.iter().map(|(node, direction)| (node.as_str(), *direction)).collect()
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Marking {
+        None,
+        LeadIn,
+        Cycle,
+    }
+
+    /// An edge per node and direction, merged into one when left and right lead to the same node.
+    struct Edge<'a> {
+        from: &'a str,
+        to: &'a str,
+        label: &'static str,
+        marking: Marking,
+    }
+
+    fn edges<'a>(input: &'a Input, ghost: Option<&'a GhostPath>) -> Vec<Edge<'a>> {
+        let lead_in = ghost.map(|ghost| GhostPath::edges(&ghost.lead_in)).unwrap_or_default();
+        let cycle = ghost.map(|ghost| GhostPath::edges(&ghost.cycle)).unwrap_or_default();
+        let marking = |from: &str, directions: &[char]| {
+            if directions.iter().any(|d| cycle.contains(&(from, *d))) {
+                Marking::Cycle
+            } else if directions.iter().any(|d| lead_in.contains(&(from, *d))) {
+                Marking::LeadIn
+            } else {
+                Marking::None
+            }
+        };
+
+        let mut edges = vec![];
+        for name in sorted_names(input) {
+            let node = &input.nodes[name];
+            if node.left == node.right {
+                edges.push(Edge { from: name, to: &node.left, label: "L/R", marking: marking(name, &['L', 'R']) });
+            } else {
+                edges.push(Edge { from: name, to: &node.left, label: "L", marking: marking(name, &['L']) });
+                edges.push(Edge { from: name, to: &node.right, label: "R", marking: marking(name, &['R']) });
+            }
+        }
+        edges
+    }
+
+    fn sorted_names(input: &Input) -> Vec<&str> {
+        let mut names: Vec<&str> = input.nodes.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum NodeKind {
+        Start,
+        End,
+        Plain,
+    }
+
+    impl NodeKind {
+        fn of(name: &str) -> Self {
+            if name.ends_with('A') {
+                NodeKind::Start
+            } else if name.ends_with('Z') {
+                NodeKind::End
+            } else {
+                NodeKind::Plain
+            }
+        }
+
+        fn name(&self) -> &'static str {
+            match self {
+                NodeKind::Start => "start",
+                NodeKind::End => "end",
+                NodeKind::Plain => "plain",
+            }
+        }
+    }
+
+    fn on_ghost_path(name: &str, ghost: Option<&GhostPath>) -> Marking {
+        let contains = |steps: &[(String, char)]| steps.iter().any(|(node, _)| node == name);
+        match ghost {
+            Some(ghost) if contains(&ghost.cycle) => Marking::Cycle,
+            Some(ghost) if contains(&ghost.lead_in) => Marking::LeadIn,
+            _ => Marking::None,
+        }
+    }
+
+    /// Graphviz, e.g. `dot -Tsvg`. Start nodes are green, end nodes red, the way of the ghost
+    /// is blue until it reaches its cycle, which is orange.
+    pub fn dot(input: &Input, ghost: Option<&GhostPath>) -> String {
+        let mut dot = String::from("digraph network {\n    node [shape=circle, fontname=monospace];\n");
+        for name in sorted_names(input) {
+            let mut attributes = vec![];
+            match NodeKind::of(name) {
+                NodeKind::Start => attributes.push("style=filled, fillcolor=palegreen".to_string()),
+                NodeKind::End => attributes.push("style=filled, fillcolor=salmon".to_string()),
+                NodeKind::Plain => {}
+            }
+            match on_ghost_path(name, ghost) {
+                Marking::Cycle => attributes.push("color=darkorange, penwidth=3".to_string()),
+                Marking::LeadIn => attributes.push("color=blue, penwidth=2".to_string()),
+                Marking::None => {}
+            }
+            if attributes.is_empty() {
+                writeln!(dot, "    \"{}\";", name).expect("writing to a string");
+            } else {
+                writeln!(dot, "    \"{}\" [{}];", name, attributes.join(", ")).expect("writing to a string");
+            }
+        }
+        for edge in edges(input, ghost) {
+            let style = match edge.marking {
+                Marking::Cycle => ", color=darkorange, penwidth=3",
+                Marking::LeadIn => ", color=blue, penwidth=2",
+                Marking::None => "",
+            };
+            writeln!(dot, "    \"{}\" -> \"{}\" [label=\"{}\"{}];", edge.from, edge.to, edge.label, style)
+                .expect("writing to a string");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// GraphML, e.g. for Gephi or yEd. Nodes have a `kind` (start, end or plain), nodes and
+    /// edges have a `ghost` attribute (lead-in, cycle or none).
+    pub fn graphml(input: &Input, ghost: Option<&GhostPath>) -> String {
+        let marking = |marking: Marking| match marking {
+            Marking::Cycle => "cycle",
+            Marking::LeadIn => "lead-in",
+            Marking::None => "none",
+        };
+
+        let mut xml = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"kind\" for=\"node\" attr.name=\"kind\" attr.type=\"string\"/>\n",
+            "  <key id=\"node_ghost\" for=\"node\" attr.name=\"ghost\" attr.type=\"string\"/>\n",
+            "  <key id=\"direction\" for=\"edge\" attr.name=\"direction\" attr.type=\"string\"/>\n",
+            "  <key id=\"edge_ghost\" for=\"edge\" attr.name=\"ghost\" attr.type=\"string\"/>\n",
+            "  <graph id=\"network\" edgedefault=\"directed\">\n",
+        ));
+        for name in sorted_names(input) {
+            writeln!(xml, "    <node id=\"{}\"><data key=\"kind\">{}</data><data key=\"node_ghost\">{}</data></node>",
+                name, NodeKind::of(name).name(), marking(on_ghost_path(name, ghost))).expect("writing to a string");
+        }
+        for edge in edges(input, ghost) {
+            writeln!(xml, "    <edge source=\"{}\" target=\"{}\"><data key=\"direction\">{}</data><data key=\"edge_ghost\">{}</data></edge>",
+                edge.from, edge.to, edge.label, marking(edge.marking)).expect("writing to a string");
+        }
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+}
+
 fn solve_part_1(filename: &str) -> Result<u32> {
     let input = parse::parse_input(filename)?;
 
@@ -124,6 +322,23 @@ fn solve_part_2(filename: &str) -> Result<u64> {
 
 fn main() -> Result<()> {
     simple_log::quick!("info");
+
+    let args: Vec<String> = env::args().collect();
+    if let Some(format @ ("dot" | "graphml")) = args.get(1).map(String::as_str) {
+        let filename = flag_value(&args, "--input").unwrap_or("src/day_08/input.txt");
+        let input = parse::parse_input(filename)?;
+        let ghost = flag_value(&args, "--ghost")
+            .map(|start| GhostPath::follow(&input, start))
+            .transpose()?;
+        if let Some(ghost) = ghost.as_ref() {
+            debug!("Ghost walks {} steps before a cycle of {} steps", ghost.lead_in.len(), ghost.cycle.len());
+        }
+        match format {
+            "dot" => print!("{}", export::dot(&input, ghost.as_ref())),
+            _ => print!("{}", export::graphml(&input, ghost.as_ref())),
+        }
+        return Ok(())
+    }
     info!("Result part 1: {}", solve_part_1("src/day_08/input.txt")?);
     info!("Result part 2: {}", solve_part_2("src/day_08/input.txt")?);
     Ok(())
@@ -132,6 +347,8 @@ fn main() -> Result<()> {
 #[cfg(test)]
 mod tests {
     use crate::{solve_part_1, solve_part_2};
+    use crate::export::{dot, graphml, GhostPath};
+    use crate::parse::parse_input;
 
     #[test]
     fn solve_test_input_1_1() {
@@ -150,4 +367,50 @@ mod tests {
         let result = solve_part_2("src/day_08/test_input_2_1.txt").unwrap();
         assert_eq!(result, 6);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn follow_ghost() {
+        let input = parse_input("src/day_08/test_input_2_1.txt").unwrap();
+        let step = |node: &str, direction| (node.to_string(), direction);
+
+        let ghost = GhostPath::follow(&input, "11A").unwrap();
+        assert_eq!(ghost.lead_in, vec![step("11A", 'L')]);
+        assert_eq!(ghost.cycle, vec![step("11B", 'R'), step("11Z", 'L')]);
+
+        let ghost = GhostPath::follow(&input, "22A").unwrap();
+        assert_eq!(ghost.lead_in, vec![step("22A", 'L')]);
+        assert_eq!(ghost.cycle.len(), 6);
+
+        assert!(GhostPath::follow(&input, "33A").is_err());
+    }
+
+    #[test]
+    fn export_dot() {
+        let input = parse_input("src/day_08/test_input_2_1.txt").unwrap();
+        let ghost = GhostPath::follow(&input, "11A").unwrap();
+        let dot = dot(&input, Some(&ghost));
+
+        assert!(dot.starts_with("digraph network {\n"));
+        assert!(dot.contains("    \"11A\" [style=filled, fillcolor=palegreen, color=blue, penwidth=2];\n"));
+        assert!(dot.contains("    \"11Z\" [style=filled, fillcolor=salmon, color=darkorange, penwidth=3];\n"));
+        assert!(dot.contains("    \"XXX\";\n"));
+        assert!(dot.contains("    \"11A\" -> \"11B\" [label=\"L\", color=blue, penwidth=2];\n"));
+        assert!(dot.contains("    \"11A\" -> \"XXX\" [label=\"R\"];\n"));
+        assert!(dot.contains("    \"11B\" -> \"11Z\" [label=\"R\", color=darkorange, penwidth=3];\n"));
+        assert!(dot.contains("    \"22B\" -> \"22C\" [label=\"L/R\"];\n"));
+        assert_eq!(dot.matches(" -> ").count(), 12);
+    }
+
+    #[test]
+    fn export_graphml() {
+        let input = parse_input("src/day_08/test_input_2_1.txt").unwrap();
+        let graphml = graphml(&input, None);
+
+        assert!(graphml.contains("<node id=\"22A\"><data key=\"kind\">start</data><data key=\"node_ghost\">none</data></node>"));
+        assert!(graphml.contains("<node id=\"22Z\"><data key=\"kind\">end</data>"));
+        assert!(graphml.contains("<edge source=\"XXX\" target=\"XXX\"><data key=\"direction\">L/R</data><data key=\"edge_ghost\">none</data></edge>"));
+        assert_eq!(graphml.matches("<node ").count(), 8);
+        assert_eq!(graphml.matches("<edge ").count(), 12);
+        assert!(graphml.ends_with("  </graph>\n</graphml>\n"));
+    }
+}
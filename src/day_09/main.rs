@@ -1,4 +1,12 @@
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use advent_of_code_2023::sequence::{extrapolate, DifferenceTable};
+use advent_of_code_2023::stream::{par_reduce_lines, reduce_lines};
+use aoc_parse::Parser;
 use anyhow::{Context, Result};
+use num_bigint::BigInt;
+use num_traits::Zero;
 
 #[macro_use]
 extern crate simple_log;
@@ -11,11 +19,17 @@ mod parse {
 
     #[derive(Debug)]
     pub struct Input {
-        pub(crate) datasets: Vec<Vec<i32>>
+        pub(crate) datasets: Vec<Vec<i64>>
+    }
+
+    /// One history per line, built once and reused for every line when streaming.
+    pub fn history_parser() -> impl Parser<Output = Vec<i64>> + Sync {
+        parser!(repeat_sep(i64, " "))
     }
 
     pub fn parse_input(filename: &str) -> Result<Input> {
-        let parser = parser!(lines(repeat_sep(i32, " ")));
+        let history_p = history_parser();
+        let parser = parser!(lines(history_p));
 
         let raw_data = read_to_string(filename)?;
         let raw_parsed = parser.parse(&raw_data).context("parse error")?;
@@ -26,42 +40,82 @@ mod parse {
     }
 }
 
-fn predict(dataset: Vec<i32>) -> Result<(i32, i32)> {
+fn solve_both_parts(filename: &str) -> Result<[i128; 2]> {
+    let input = parse::parse_input(filename)?;
 
-    // derive the next row from the different between items
-    let derived_row = dataset.windows(2)
-        .map(|window| window[1] - window[0])
-        .collect::<Vec<i32>>();
+    let mut total_next = 0_i128;
+    let mut total_prev = 0_i128;
+    for (idx, dataset) in input.datasets.iter().enumerate() {
+        let (prev, next) = extrapolate(dataset)
+            .with_context(|| format!("can't extrapolate history {}", idx + 1))?;
+        total_next = total_next.checked_add(next).context("total too large")?;
+        total_prev = total_prev.checked_add(prev).context("total too large")?;
+    }
 
-    let first = dataset.get(0).context("last element")?;
-    let last = dataset.last().context("last element")?;
-    let last_derived = derived_row.last().context("no last elem")?;
-    let first_derived = derived_row.first().context("no first elem")?;
+    Ok([total_prev, total_next])
+}
 
-    if derived_row.iter().all(|v| v == &0) {
-        Ok((first - first_derived, last + last_derived))
+/// Same as [solve_both_parts], but reads and solves one history at a time (or chunks of them in
+/// parallel), so the input never has to fit into memory.
+fn solve_streaming<R: BufRead>(reader: R, parallel: bool) -> Result<[i128; 2]> {
+    let history_p = parse::history_parser();
+    let predict = |line: &str| {
+        let (prev, next) = extrapolate(&history_p.parse(line).context("parse error")?)?;
+        Ok([prev, next])
+    };
+    let add = |a: [i128; 2], b: [i128; 2]| Ok([
+        a[0].checked_add(b[0]).context("total too large")?,
+        a[1].checked_add(b[1]).context("total too large")?,
+    ]);
+
+    if parallel {
+        par_reduce_lines(reader, 10_000, predict, || [0, 0], add)
     } else {
-        let (predicted_prev, predicted_next) = predict(derived_row).unwrap();
-        Ok((first - predicted_prev, last + predicted_next))
+        reduce_lines(reader, predict, || [0, 0], add)
     }
 }
 
-fn solve_both_parts(filename: &str) -> Result<[i128; 2]> {
+/// Sum of the values of all histories at the given index, where 0 is the first value.
+fn solve_at(filename: &str, index: i64) -> Result<BigInt> {
     let input = parse::parse_input(filename)?;
 
-    let mut total_next = 0_i128;
-    let mut total_prev = 0_i128;
-    for dataset in input.datasets {
-        let (prev, next) = predict(dataset.clone()).context("predict works")?;
-        total_next += next as i128;
-        total_prev += prev as i128;
+    let mut total = BigInt::zero();
+    for (idx, dataset) in input.datasets.iter().enumerate() {
+        let polynomial = DifferenceTable::new(dataset)
+            .with_context(|| format!("no polynomial for history {}", idx + 1))?
+            .polynomial();
+        debug!("History {} is {}", idx + 1, polynomial);
+        total += polynomial.evaluate(index);
     }
 
-    Ok([total_prev, total_next])
+    Ok(total)
 }
 
 fn main() -> Result<()> {
     simple_log::quick!("info");
+
+    let args: Vec<String> = env::args().collect();
+    if let Some("at") = args.get(1).map(String::as_str) {
+        let index = args.get(2).context("usage: day_09 at <index>")?.parse()?;
+        info!("Sum at index {}: {}", index, solve_at("src/day_09/input.txt", index)?);
+        return Ok(())
+    }
+    if let Some("stream") = args.get(1).map(String::as_str) {
+        let filename = args.get(2).context("usage: day_09 stream <file> [--parallel]")?;
+        let parallel = args.iter().any(|arg| arg == "--parallel");
+        let [prev, next] = solve_streaming(BufReader::new(File::open(filename)?), parallel)?;
+        info!("Result part 1: {}", next);
+        info!("Result part 2: {}", prev);
+        return Ok(())
+    }
+    if let Some("polynomials") = args.get(1).map(String::as_str) {
+        let input = parse::parse_input("src/day_09/input.txt")?;
+        for (idx, dataset) in input.datasets.iter().enumerate() {
+            let polynomial = DifferenceTable::new(dataset)?.polynomial();
+            info!("History {}: {}  =  {}", idx + 1, polynomial, polynomial.newton_form());
+        }
+        return Ok(())
+    }
     let [r2, r1] = solve_both_parts("src/day_09/input.txt")?;
     assert_eq!(2175229206, r1);
     info!("Result part 1: {}", r1);
@@ -72,7 +126,10 @@ fn main() -> Result<()> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{solve_both_parts};
+    use num_bigint::BigInt;
+    use std::fs::File;
+    use std::io::BufReader;
+    use crate::{solve_at, solve_both_parts, solve_streaming};
 
     #[test]
     fn solve_test_input() {
@@ -80,4 +137,20 @@ mod tests {
         assert_eq!(result_1, 114);
         assert_eq!(result_2, 2);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn solve_test_input_at() {
+        assert_eq!(solve_at("src/day_09/test_input.txt", 6).unwrap(), BigInt::from(114));
+        assert_eq!(solve_at("src/day_09/test_input.txt", -1).unwrap(), BigInt::from(2));
+    }
+
+    #[test]
+    fn solve_test_input_streaming() {
+        for parallel in [false, true] {
+            let reader = BufReader::new(File::open("src/day_09/test_input.txt").unwrap());
+            let [result_2, result_1] = solve_streaming(reader, parallel).unwrap();
+            assert_eq!(result_1, 114);
+            assert_eq!(result_2, 2);
+        }
+    }
+}
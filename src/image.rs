@@ -0,0 +1,453 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use anyhow::{ensure, Context, Result};
+use crate::visualize::{Color, Frame, Grid};
+
+/// Pixels row by row, starting at the top left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+impl Image {
+    /// Every cell of the grid becomes a square of `cell_size` pixels in the colour `color_of`
+    /// picks for its position and character.
+    pub fn from_grid<F: Fn(usize, usize, char) -> Color>(grid: &Grid, cell_size: usize, color_of: F) -> Self {
+        let width = grid.width * cell_size;
+        let height = grid.height * cell_size;
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..grid.height {
+            let row: Vec<Color> = (0..grid.width)
+                .flat_map(|x| {
+                    let color = color_of(x, y, grid.get(x, y).expect("inside the grid"));
+                    std::iter::repeat_n(color, cell_size)
+                })
+                .collect();
+            for _ in 0..cell_size {
+                pixels.extend_from_slice(&row);
+            }
+        }
+        Image { width, height, pixels }
+    }
+
+    /// Paints every cell of a frame in the colour its overlays give it, backgrounds before
+    /// foregrounds. Cells without any take `empty` if they are blank or a dot, otherwise gray.
+    pub fn from_frame(frame: &Frame, cell_size: usize, empty: Color) -> Self {
+        let (grid, styles) = frame.styled_cells();
+        Image::from_grid(&grid, cell_size, |x, y, c| {
+            let style = styles[y * grid.width + x];
+            match (style.background, style.foreground) {
+                (Some(color), _) | (None, Some(color)) => color,
+                (None, None) if c == '.' || c == ' ' => empty,
+                (None, None) => Color::GRAY,
+            }
+        })
+    }
+
+    /// A truecolour PNG. The image data is deflated with the fixed Huffman codes, only repeating
+    /// the pixel to the left or the row above, which is all grids need to shrink a lot.
+    pub fn write_png<W: Write>(&self, out: &mut W) -> Result<()> {
+        ensure!(self.width > 0 && self.height > 0, "can't write an empty image");
+
+        let mut raw = Vec::with_capacity((self.width * 3 + 1) * self.height);
+        for row in self.pixels.chunks(self.width) {
+            raw.push(0); // no filter
+            for pixel in row {
+                raw.extend_from_slice(&[pixel.r, pixel.g, pixel.b]);
+            }
+        }
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        header.extend_from_slice(&[8, 2, 0, 0, 0]); // 8 bit RGB, deflate, no filter, no interlace
+
+        out.write_all(b"\x89PNG\r\n\x1a\n")?;
+        write_png_chunk(out, b"IHDR", &header)?;
+        write_png_chunk(out, b"IDAT", &zlib(&raw, self.width * 3 + 1))?;
+        write_png_chunk(out, b"IEND", &[])?;
+        Ok(())
+    }
+}
+
+fn write_png_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data.iter()));
+    out.write_all(&crc.to_be_bytes())?;
+    Ok(())
+}
+
+fn crc32<'a, I: Iterator<Item=&'a u8>>(bytes: I) -> u32 {
+    let mut crc = 0xffff_ffff_u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1_u32, 0_u32);
+    for byte in bytes {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// Writes bits starting with the least significant one, as both deflate and GIF's LZW want.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, bits: u32) {
+        self.buffer |= value << self.bits;
+        self.bits += bits;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    /// Huffman codes go most significant bit first.
+    fn write_reversed(&mut self, code: u32, bits: u32) {
+        self.write(code.reverse_bits() >> (32 - bits), bits);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/// `(code, extra bits, first value)` of the deflate length codes 257 to 285.
+const LENGTH_CODES: [(u32, u32, usize); 29] = [
+    (257, 0, 3), (258, 0, 4), (259, 0, 5), (260, 0, 6), (261, 0, 7), (262, 0, 8), (263, 0, 9), (264, 0, 10),
+    (265, 1, 11), (266, 1, 13), (267, 1, 15), (268, 1, 17), (269, 2, 19), (270, 2, 23), (271, 2, 27),
+    (272, 2, 31), (273, 3, 35), (274, 3, 43), (275, 3, 51), (276, 3, 59), (277, 4, 67), (278, 4, 83),
+    (279, 4, 99), (280, 4, 115), (281, 5, 131), (282, 5, 163), (283, 5, 195), (284, 5, 227), (285, 0, 258),
+];
+
+fn write_literal_or_length(bits: &mut BitWriter, value: u32) {
+    match value {
+        0..=143 => bits.write_reversed(0x30 + value, 8),
+        144..=255 => bits.write_reversed(0x190 + value - 144, 9),
+        256..=279 => bits.write_reversed(value - 256, 7),
+        _ => bits.write_reversed(0xc0 + value - 280, 8),
+    }
+}
+
+fn write_match(bits: &mut BitWriter, length: usize, distance: usize) {
+    let (code, extra, first) = *LENGTH_CODES.iter()
+        .rev()
+        .find(|(_, _, first)| *first <= length)
+        .expect("lengths start at 3");
+    write_literal_or_length(bits, code);
+    bits.write((length - first) as u32, extra);
+
+    // distance codes come in pairs sharing the number of extra bits
+    let (code, extra, first) = if distance <= 4 {
+        (distance as u32 - 1, 0, distance)
+    } else {
+        let extra = usize::BITS - 1 - (distance - 1).leading_zeros() - 1;
+        let half = ((distance - 1) >> extra) & 1;
+        let first = ((2 + half) << extra) + 1;
+        (2 * (extra + 1) + half as u32, extra, first)
+    };
+    bits.write_reversed(code, 5);
+    bits.write((distance - first) as u32, extra);
+}
+
+/// A zlib stream with a single fixed Huffman block, matching either the previous pixel or the
+/// same bytes one `stride` back.
+fn zlib(data: &[u8], stride: usize) -> Vec<u8> {
+    let mut bits = BitWriter::default();
+    bits.write(0x78, 8);
+    bits.write(0x01, 8);
+    bits.write(1, 1); // final block
+    bits.write(1, 2); // fixed Huffman codes
+
+    let match_length = |pos: usize, distance: usize| {
+        if distance > pos || distance > 32768 {
+            return 0;
+        }
+        data[pos..].iter()
+            .zip(data[(pos - distance)..].iter())
+            .take(258)
+            .take_while(|(a, b)| a == b)
+            .count()
+    };
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let (length, distance) = [3, stride].iter()
+            .map(|distance| (match_length(pos, *distance), *distance))
+            .max()
+            .expect("two candidates");
+        if length >= 3 {
+            write_match(&mut bits, length, distance);
+            pos += length;
+        } else {
+            write_literal_or_length(&mut bits, data[pos] as u32);
+            pos += 1;
+        }
+    }
+    write_literal_or_length(&mut bits, 256);
+
+    let mut compressed = bits.finish();
+    compressed.extend_from_slice(&adler32(data).to_be_bytes());
+    compressed
+}
+
+/// A vector image of the grid: a rectangle per run of equally coloured cells, plus the
+/// characters `show_char` asks for, in black or white depending on the background.
+pub fn grid_svg<F, S>(grid: &Grid, cell_size: usize, color_of: F, show_char: S) -> String
+    where F: Fn(usize, usize, char) -> Color,
+          S: Fn(char) -> bool {
+    let mut svg = String::new();
+    let (width, height) = (grid.width * cell_size, grid.height * cell_size);
+    writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
+        width, height, width, height).expect("writing to a string");
+    writeln!(svg, "<g font-family=\"monospace\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\">",
+        cell_size).expect("writing to a string");
+
+    for y in 0..grid.height {
+        let mut x = 0;
+        while x < grid.width {
+            let color = color_of(x, y, grid.get(x, y).expect("inside the grid"));
+            let run = (x..grid.width)
+                .take_while(|x| color_of(*x, y, grid.get(*x, y).expect("inside the grid")) == color)
+                .count();
+            writeln!(svg, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                x * cell_size, y * cell_size, run * cell_size, cell_size, hex(color)).expect("writing to a string");
+            x += run;
+        }
+    }
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            let c = grid.get(x, y).expect("inside the grid");
+            if !show_char(c) {
+                continue;
+            }
+            let background = color_of(x, y, c);
+            let luminance = 299 * background.r as u32 + 587 * background.g as u32 + 114 * background.b as u32;
+            let text_color = if luminance > 128_000 { Color::BLACK } else { Color::WHITE };
+            writeln!(svg, "<text x=\"{}\" y=\"{}\" fill=\"{}\">{}</text>",
+                x * cell_size + cell_size / 2, y * cell_size + cell_size / 2, hex(text_color), xml_escape(c))
+                .expect("writing to a string");
+        }
+    }
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+fn hex(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+fn xml_escape(c: char) -> String {
+    match c {
+        '&' => "&amp;".to_string(),
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        _ => c.to_string()
+    }
+}
+
+/// An animated GIF looping forever, showing every frame for `delay_ms` (rounded to the
+/// hundredths of a second GIF counts in). All frames need the same size and together at most
+/// 256 different colours.
+pub fn write_gif<W: Write>(out: &mut W, frames: &[Image], delay_ms: u32) -> Result<()> {
+    let first = frames.first().context("an animation needs at least one frame")?;
+    let (width, height) = (first.width, first.height);
+    ensure!(width > 0 && height > 0, "can't write an empty image");
+    ensure!(width <= u16::MAX as usize && height <= u16::MAX as usize, "{}x{} is too large for a GIF", width, height);
+    ensure!(frames.iter().all(|frame| frame.width == width && frame.height == height), "all frames need the same size");
+
+    let mut palette: Vec<Color> = vec![];
+    let mut palette_index: HashMap<Color, u8> = HashMap::new();
+    for pixel in frames.iter().flat_map(|frame| frame.pixels.iter()) {
+        if !palette_index.contains_key(pixel) {
+            ensure!(palette.len() < 256, "more than 256 colours");
+            palette_index.insert(*pixel, palette.len() as u8);
+            palette.push(*pixel);
+        }
+    }
+    // the colour table has 2^(size + 1) entries, and LZW needs codes of at least 2 bits
+    let table_bits = (usize::BITS - (palette.len().max(2) - 1).leading_zeros()).max(1);
+    palette.resize(1 << table_bits, Color::BLACK);
+
+    out.write_all(b"GIF89a")?;
+    out.write_all(&(width as u16).to_le_bytes())?;
+    out.write_all(&(height as u16).to_le_bytes())?;
+    out.write_all(&[0xf0 | (table_bits as u8 - 1), 0, 0])?;
+    for color in palette.iter() {
+        out.write_all(&[color.r, color.g, color.b])?;
+    }
+    out.write_all(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00")?;
+
+    let delay = ((delay_ms + 5) / 10).min(u16::MAX as u32) as u16;
+    for frame in frames {
+        out.write_all(&[0x21, 0xf9, 0x04, 0x00])?;
+        out.write_all(&delay.to_le_bytes())?;
+        out.write_all(&[0x00, 0x00])?;
+
+        out.write_all(&[0x2c, 0, 0, 0, 0])?;
+        out.write_all(&(width as u16).to_le_bytes())?;
+        out.write_all(&(height as u16).to_le_bytes())?;
+        out.write_all(&[0x00])?;
+
+        let min_code_size = table_bits.max(2);
+        let indices: Vec<u8> = frame.pixels.iter().map(|pixel| palette_index[pixel]).collect();
+        out.write_all(&[min_code_size as u8])?;
+        for block in lzw(&indices, min_code_size).chunks(255) {
+            out.write_all(&[block.len() as u8])?;
+            out.write_all(block)?;
+        }
+        out.write_all(&[0x00])?;
+    }
+    out.write_all(&[0x3b])?;
+    Ok(())
+}
+
+/// GIF flavoured LZW: codes grow from `min_code_size + 1` up to 12 bits, then the table starts
+/// over with a clear code.
+fn lzw(indices: &[u8], min_code_size: u32) -> Vec<u8> {
+    let clear = 1_u32 << min_code_size;
+    let end = clear + 1;
+    let mut bits = BitWriter::default();
+    let mut table: HashMap<(u32, u8), u32> = HashMap::new();
+    let mut next_code = end + 1;
+    let mut code_size = min_code_size + 1;
+
+    bits.write(clear, code_size);
+    let mut current: Option<u32> = None;
+    for index in indices {
+        let Some(prefix) = current else {
+            current = Some(*index as u32);
+            continue;
+        };
+        if let Some(code) = table.get(&(prefix, *index)) {
+            current = Some(*code);
+            continue;
+        }
+
+        bits.write(prefix, code_size);
+        if next_code == 4096 {
+            bits.write(clear, code_size);
+            table.clear();
+            next_code = end + 1;
+            code_size = min_code_size + 1;
+        } else {
+            table.insert((prefix, *index), next_code);
+            if next_code == 1 << code_size {
+                code_size += 1;
+            }
+            next_code += 1;
+        }
+        current = Some(*index as u32);
+    }
+    if let Some(prefix) = current {
+        bits.write(prefix, code_size);
+    }
+    bits.write(end, code_size);
+    bits.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::image::{adler32, crc32, grid_svg, write_gif, zlib, Image};
+    use crate::visualize::{Color, Frame, Grid, Overlay};
+
+    fn checkerboard() -> Image {
+        let grid = Grid::from_lines(&["#.#", ".#."]);
+        Image::from_grid(&grid, 2, |_, _, c| if c == '#' { Color::RED } else { Color::WHITE })
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"IEND".iter()), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn image_from_grid() {
+        let image = checkerboard();
+        assert_eq!((image.width, image.height), (6, 4));
+        assert_eq!(image.pixels[0..6], [Color::RED, Color::RED, Color::WHITE, Color::WHITE, Color::RED, Color::RED]);
+        assert_eq!(image.pixels[6..12], image.pixels[0..6]);
+        assert_eq!(image.pixels[12], Color::WHITE);
+    }
+
+    #[test]
+    fn image_from_frame() {
+        let frame = Frame::new(Grid::from_lines(&["#.", ".x"]))
+            .with(Overlay::Highlight { cells: vec![(1, 1)], color: Color::GREEN });
+        let image = Image::from_frame(&frame, 1, Color::BLACK);
+        assert_eq!(image.pixels, vec![Color::GRAY, Color::BLACK, Color::BLACK, Color::GREEN]);
+    }
+
+    #[test]
+    fn deflate_fixed_huffman() {
+        // both inflate fine with zlib, the second one repeats "aaa" from 3 bytes back
+        assert_eq!(zlib(b"a", 100), vec![0x78, 0x01, 0x4b, 0x04, 0x00, 0x00, 0x62, 0x00, 0x62]);
+        assert_eq!(zlib(b"aaaaaa", 100), vec![0x78, 0x01, 0x4b, 0x4c, 0x4c, 0x04, 0x22, 0x00, 0x07, 0xfb, 0x02, 0x47]);
+    }
+
+    #[test]
+    fn write_png() {
+        let mut png = vec![];
+        checkerboard().write_png(&mut png).unwrap();
+
+        assert_eq!(png[0..8], *b"\x89PNG\r\n\x1a\n");
+        assert_eq!(png[8..16], *b"\x00\x00\x00\x0dIHDR");
+        assert_eq!(png[16..29], [0, 0, 0, 6, 0, 0, 0, 4, 8, 2, 0, 0, 0]);
+        assert_eq!(png[png.len() - 12..], *b"\x00\x00\x00\x00IEND\xae\x42\x60\x82");
+        assert!(Image { width: 0, height: 0, pixels: vec![] }.write_png(&mut vec![]).is_err());
+    }
+
+    #[test]
+    fn write_svg() {
+        let grid = Grid::from_lines(&["#..", "<.#"]);
+        let svg = grid_svg(&grid, 10, |_, _, c| if c == '.' { Color::WHITE } else { Color::BLUE }, |c| c != '.');
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"30\" height=\"20\""));
+        assert!(svg.contains("<rect x=\"10\" y=\"0\" width=\"20\" height=\"10\" fill=\"#ffffff\"/>"));
+        assert!(svg.contains("<text x=\"5\" y=\"15\" fill=\"#ffffff\">&lt;</text>"));
+        assert_eq!(svg.matches("<rect").count(), 5);
+    }
+
+    #[test]
+    fn write_animated_gif() {
+        let mut inverted = checkerboard();
+        for pixel in inverted.pixels.iter_mut() {
+            *pixel = if *pixel == Color::RED { Color::WHITE } else { Color::RED };
+        }
+
+        let mut gif = vec![];
+        write_gif(&mut gif, &[checkerboard(), inverted], 100).unwrap();
+        assert_eq!(gif[0..13], *b"GIF89a\x06\x00\x04\x00\xf0\x00\x00");
+        assert_eq!(gif[13..19], [220, 50, 47, 255, 255, 255]);
+        assert_eq!(gif[19..38], *b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+        assert_eq!(gif[38..46], [0x21, 0xf9, 0x04, 0x00, 10, 0, 0, 0]);
+        assert_eq!(gif.last(), Some(&0x3b));
+
+        let other_size = Image { width: 1, height: 1, pixels: vec![Color::RED] };
+        assert!(write_gif(&mut vec![], &[checkerboard(), other_size], 100).is_err());
+        assert!(write_gif(&mut vec![], &[], 100).is_err());
+    }
+}
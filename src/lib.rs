@@ -7,14 +7,47 @@ pub mod common {
         // Open the file in read-only mode.
         let file = File::open(filename.into()).unwrap();
         // Read the file line by line, and return an iterator of the lines of the file.
-        return BufReader::new(file).lines()
+        BufReader::new(file).lines()
     }
 
+    #[allow(clippy::lines_filter_map_ok)]
     pub fn read_valid_lines<S: Into<String>>(filename: S) -> impl Iterator<Item=String> + 'static {
         // Open the file in read-only mode.
         let file = File::open(filename.into()).unwrap();
         // Read the file line by line, and return an iterator of the lines of the file.
-        return BufReader::new(file).lines()
-            .filter_map(|l| l.ok());
+        BufReader::new(file).lines()
+            .filter_map(|l| l.ok())
     }
-}
\ No newline at end of file
+
+    /// The value following `flag` in the command line arguments, if the flag is given at all.
+    pub fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|idx| args.get(idx + 1))
+            .map(String::as_str)
+    }
+}
+
+pub mod random {
+    /// Tiny xorshift generator, good enough to generate puzzle inputs.
+    pub struct Rng(u64);
+
+    impl Rng {
+        pub fn new(seed: u64) -> Self {
+            Rng(seed.max(1))
+        }
+
+        /// A number in `0..below`.
+        pub fn next(&mut self, below: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % below as u64) as usize
+        }
+    }
+}
+
+pub mod sequence;
+pub mod stream;
+pub mod image;
+pub mod visualize;
@@ -0,0 +1,275 @@
+use std::fmt;
+use std::fmt::Formatter;
+use anyhow::{bail, Context, Result};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, Zero};
+
+/// The finite differences of a sequence generated by a polynomial: every row holds the
+/// differences between neighbouring values of the row before, the first row is the sequence
+/// itself. The last row is the first one where all values are equal, its index is the degree of
+/// the polynomial.
+///
+/// All arithmetic is done on `i128` and checked, so extrapolating either gives the exact value
+/// or an error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DifferenceTable {
+    pub rows: Vec<Vec<i128>>,
+}
+
+impl DifferenceTable {
+    /// Derives differences until a row is constant. Fails if there is no such row with at least
+    /// two values, as then the values don't tell which polynomial they come from.
+    pub fn new(values: &[i64]) -> Result<Self> {
+        let mut rows: Vec<Vec<i128>> = vec![values.iter().map(|v| *v as i128).collect()];
+
+        loop {
+            let row = rows.last().expect("at least the values");
+            if row.len() < 2 {
+                bail!("not polynomial within {} values", values.len());
+            }
+            if row.iter().all(|v| v == &row[0]) {
+                return Ok(DifferenceTable { rows });
+            }
+
+            let derived_row = row.windows(2)
+                .map(|window| window[1].checked_sub(window[0]))
+                .collect::<Option<Vec<i128>>>()
+                .context("overflow while deriving differences")?;
+            rows.push(derived_row);
+        }
+    }
+
+    pub fn degree(&self) -> usize {
+        self.rows.len() - 1
+    }
+
+    /// The `k` values following the sequence, nearest first.
+    pub fn forward(&self, k: usize) -> Result<Vec<i128>> {
+        let mut last_column: Vec<i128> = self.rows.iter()
+            .map(|row| *row.last().expect("rows are not empty"))
+            .collect();
+
+        (0..k).map(|_| extend(&mut last_column, |value, below| value.checked_add(below)))
+            .collect()
+    }
+
+    /// The `k` values preceding the sequence, nearest first.
+    pub fn backward(&self, k: usize) -> Result<Vec<i128>> {
+        let mut first_column: Vec<i128> = self.rows.iter()
+            .map(|row| row[0])
+            .collect();
+
+        (0..k).map(|_| extend(&mut first_column, |value, below| value.checked_sub(below)))
+            .collect()
+    }
+
+    /// The polynomial generating the sequence, the first value of the sequence is at `x = 0`.
+    pub fn polynomial(&self) -> Polynomial {
+        Polynomial::new(self.rows.iter().map(|row| BigInt::from(row[0])).collect())
+    }
+}
+
+/// A polynomial in both Newton forward form, `f(x) = c0 + c1*C(x, 1) + c2*C(x, 2) + ...` with
+/// `C(x, j)` being the binomial coefficient "x choose j", and in standard form
+/// `f(x) = a0 + a1*x + a2*x^2 + ...`. The Newton coefficients of a sequence are just the first
+/// column of its difference table and always are integers, the standard ones may be fractions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polynomial {
+    pub newton: Vec<BigInt>,
+    /// Lowest power first
+    pub standard: Vec<BigRational>,
+}
+
+impl Polynomial {
+    pub fn new(newton: Vec<BigInt>) -> Self {
+        let mut standard = vec![BigRational::zero(); newton.len()];
+
+        // C(x, j) = x * (x-1) * ... * (x-j+1) / j!, so expand the product step by step
+        let mut falling_factorial = vec![BigInt::one()];
+        let mut factorial = BigInt::one();
+        for (j, coefficient) in newton.iter().enumerate() {
+            if j > 0 {
+                let shifted: Vec<BigInt> = std::iter::once(BigInt::zero())
+                    .chain(falling_factorial.iter().cloned())
+                    .collect();
+                let offset = BigInt::from(j - 1);
+                falling_factorial = shifted.iter()
+                    .zip(falling_factorial.iter().chain(std::iter::once(&BigInt::zero())))
+                    .map(|(shifted, original)| shifted - original * &offset)
+                    .collect();
+                factorial *= j;
+            }
+            for (power, factor) in falling_factorial.iter().enumerate() {
+                standard[power] += BigRational::new(coefficient * factor, factorial.clone());
+            }
+        }
+
+        Polynomial { newton, standard }
+    }
+
+    /// Exact value at `x`, which can be far beyond (or before) the original sequence.
+    pub fn evaluate(&self, x: i64) -> BigInt {
+        let x = BigInt::from(x);
+        let mut binomial = BigInt::one();
+        let mut total = BigInt::zero();
+
+        for (j, coefficient) in self.newton.iter().enumerate() {
+            if j > 0 {
+                // C(x, j) = C(x, j-1) * (x-j+1) / j, the division is always exact
+                binomial = binomial * (&x - (j - 1)) / j;
+            }
+            total += coefficient * &binomial;
+        }
+        total
+    }
+
+    /// Formats the Newton forward form, e.g. `1 + 3*C(x, 1) + 2*C(x, 2)`.
+    pub fn newton_form(&self) -> String {
+        let terms = self.newton.iter()
+            .enumerate()
+            .filter(|(_, coefficient)| !coefficient.is_zero())
+            .map(|(j, coefficient)| match j {
+                0 => (coefficient.is_negative(), coefficient.abs().to_string()),
+                _ => (coefficient.is_negative(), format!("{}*C(x, {})", coefficient.abs(), j))
+            });
+        join_terms(terms)
+    }
+}
+
+/// Formats the standard form, highest power first, e.g. `1/2*x^2 - 3*x + 7`.
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let terms = self.standard.iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, coefficient)| !coefficient.is_zero())
+            .map(|(power, coefficient)| {
+                let factor = if coefficient.abs().is_one() && power > 0 {
+                    String::new()
+                } else if power > 0 {
+                    format!("{}*", coefficient.abs())
+                } else {
+                    coefficient.abs().to_string()
+                };
+                let variable = match power {
+                    0 => String::new(),
+                    1 => "x".to_string(),
+                    _ => format!("x^{}", power)
+                };
+                (coefficient.is_negative(), format!("{}{}", factor, variable))
+            });
+        write!(f, "{}", join_terms(terms))
+    }
+}
+
+fn join_terms<I: Iterator<Item=(bool, String)>>(terms: I) -> String {
+    let mut formatted = String::new();
+    for (negative, term) in terms {
+        match (formatted.is_empty(), negative) {
+            (true, false) => formatted.push_str(&term),
+            (true, true) => formatted.push_str(&format!("-{}", term)),
+            (false, false) => formatted.push_str(&format!(" + {}", term)),
+            (false, true) => formatted.push_str(&format!(" - {}", term)),
+        }
+    }
+    if formatted.is_empty() {
+        formatted.push('0');
+    }
+    formatted
+}
+
+/// Moves a column of the difference table one step, starting at the constant row at the bottom,
+/// and returns the new value of the sequence.
+fn extend<F: Fn(i128, i128) -> Option<i128>>(column: &mut [i128], step: F) -> Result<i128> {
+    for row in (0..(column.len() - 1)).rev() {
+        column[row] = step(column[row], column[row + 1]).context("overflow while extrapolating")?;
+    }
+    Ok(column[0])
+}
+
+/// The value right before and right after the sequence.
+pub fn extrapolate(values: &[i64]) -> Result<(i128, i128)> {
+    let table = DifferenceTable::new(values)?;
+    Ok((table.backward(1)?[0], table.forward(1)?[0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigInt;
+    use crate::sequence::{extrapolate, DifferenceTable};
+
+    #[test]
+    fn extrapolate_puzzle_examples() {
+        assert_eq!(extrapolate(&[0, 3, 6, 9, 12, 15]).unwrap(), (-3, 18));
+        assert_eq!(extrapolate(&[1, 3, 6, 10, 15, 21]).unwrap(), (0, 28));
+        assert_eq!(extrapolate(&[10, 13, 16, 21, 30, 45]).unwrap(), (5, 68));
+    }
+
+    #[test]
+    fn degree_and_multiple_steps() {
+        let squares = DifferenceTable::new(&[1, 4, 9, 16]).unwrap();
+        assert_eq!(squares.degree(), 2);
+        assert_eq!(squares.forward(3).unwrap(), vec![25, 36, 49]);
+        assert_eq!(squares.backward(3).unwrap(), vec![0, 1, 4]);
+
+        let constant = DifferenceTable::new(&[7, 7]).unwrap();
+        assert_eq!(constant.degree(), 0);
+        assert_eq!(constant.forward(2).unwrap(), vec![7, 7]);
+    }
+
+    #[test]
+    fn not_polynomial() {
+        let error = DifferenceTable::new(&[1, 2, 4, 8, 16]).unwrap_err();
+        assert_eq!(error.to_string(), "not polynomial within 5 values");
+        assert!(DifferenceTable::new(&[42]).is_err());
+        assert!(DifferenceTable::new(&[]).is_err());
+    }
+
+    #[test]
+    fn large_values() {
+        let (prev, next) = extrapolate(&[i64::MAX - 2, i64::MAX - 1, i64::MAX]).unwrap();
+        assert_eq!(prev, i64::MAX as i128 - 3);
+        assert_eq!(next, i64::MAX as i128 + 1);
+
+        let alternating: Vec<i64> = (0..70).map(|i| if i % 2 == 0 { i64::MAX } else { -i64::MAX }).collect();
+        assert!(DifferenceTable::new(&alternating).is_err());
+
+        let steep = DifferenceTable { rows: vec![vec![i128::MAX - 2, i128::MAX - 1], vec![1, 1]] };
+        assert_eq!(steep.forward(1).unwrap(), vec![i128::MAX]);
+        assert!(steep.forward(2).is_err());
+    }
+
+    #[test]
+    fn polynomial_forms() {
+        let polynomial = DifferenceTable::new(&[1, 3, 6, 10, 15, 21]).unwrap().polynomial();
+        assert_eq!(polynomial.newton_form(), "1 + 2*C(x, 1) + 1*C(x, 2)");
+        assert_eq!(polynomial.to_string(), "1/2*x^2 + 3/2*x + 1");
+
+        let polynomial = DifferenceTable::new(&[10, 13, 16, 21, 30, 45]).unwrap().polynomial();
+        assert_eq!(polynomial.newton_form(), "10 + 3*C(x, 1) + 2*C(x, 3)");
+        assert_eq!(polynomial.to_string(), "1/3*x^3 - x^2 + 11/3*x + 10");
+
+        let polynomial = DifferenceTable::new(&[0, -3, -6]).unwrap().polynomial();
+        assert_eq!(polynomial.newton_form(), "-3*C(x, 1)");
+        assert_eq!(polynomial.to_string(), "-3*x");
+
+        assert_eq!(DifferenceTable::new(&[0, 0]).unwrap().polynomial().to_string(), "0");
+    }
+
+    #[test]
+    fn evaluate_polynomial() {
+        let table = DifferenceTable::new(&[10, 13, 16, 21, 30, 45]).unwrap();
+        let polynomial = table.polynomial();
+        for (x, expected) in (6..20).zip(table.forward(14).unwrap()) {
+            assert_eq!(polynomial.evaluate(x), BigInt::from(expected));
+        }
+        for (x, expected) in (-10..0).rev().zip(table.backward(10).unwrap()) {
+            assert_eq!(polynomial.evaluate(x), BigInt::from(expected));
+        }
+
+        let squares = DifferenceTable::new(&[1, 4, 9, 16]).unwrap().polynomial();
+        let far_away: BigInt = "1000000000002000000000001".parse().unwrap();
+        assert_eq!(squares.evaluate(1_000_000_000_000), far_away);
+    }
+}
@@ -0,0 +1,83 @@
+use std::io::BufRead;
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+
+/// Solves every line of the input on its own with `map` and combines the results with `reduce`,
+/// starting from `identity()`. Lines are read one at a time, so the input can be far larger than
+/// the available memory.
+pub fn reduce_lines<R, T, M, C, I>(reader: R, map: M, identity: I, reduce: C) -> Result<T>
+    where R: BufRead,
+          M: Fn(&str) -> Result<T>,
+          C: Fn(T, T) -> Result<T>,
+          I: Fn() -> T {
+    let mut result = identity();
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line.with_context(|| format!("can't read line {}", idx + 1))?;
+        let solved = map(&line).with_context(|| format!("can't solve line {}", idx + 1))?;
+        result = reduce(result, solved)?;
+    }
+    Ok(result)
+}
+
+/// Like [reduce_lines], but reads chunks of `chunk_size` lines and solves the lines of each chunk
+/// in parallel. Only one chunk is held in memory at a time.
+pub fn par_reduce_lines<R, T, M, C, I>(reader: R, chunk_size: usize, map: M, identity: I, reduce: C) -> Result<T>
+    where R: BufRead,
+          T: Send,
+          M: Fn(&str) -> Result<T> + Sync,
+          C: Fn(T, T) -> Result<T> + Sync,
+          I: Fn() -> T + Sync {
+    let mut result = identity();
+    let mut lines = reader.lines().enumerate();
+    let mut chunk: Vec<(usize, String)> = Vec::with_capacity(chunk_size);
+
+    loop {
+        chunk.clear();
+        for (idx, line) in lines.by_ref().take(chunk_size.max(1)) {
+            chunk.push((idx, line.with_context(|| format!("can't read line {}", idx + 1))?));
+        }
+        if chunk.is_empty() {
+            return Ok(result);
+        }
+
+        let solved = chunk.par_iter()
+            .map(|(idx, line)| map(line).with_context(|| format!("can't solve line {}", idx + 1)))
+            .try_reduce(&identity, &reduce)?;
+        result = reduce(result, solved)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use anyhow::Context;
+    use crate::stream::{par_reduce_lines, reduce_lines};
+
+    fn line_sum(line: &str) -> anyhow::Result<u64> {
+        line.split_whitespace()
+            .map(|n| n.parse::<u64>().context("not a number"))
+            .sum()
+    }
+
+    #[test]
+    fn reduce_all_lines() {
+        let input = "1 2 3\n4 5\n\n6\n";
+        let add = |a: u64, b: u64| Ok(a + b);
+
+        assert_eq!(reduce_lines(Cursor::new(input), line_sum, || 0, add).unwrap(), 21);
+        for chunk_size in [0, 1, 2, 3, 10] {
+            assert_eq!(par_reduce_lines(Cursor::new(input), chunk_size, line_sum, || 0, add).unwrap(), 21);
+        }
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let input = "1 2 3\n4 x\n6\n";
+        let add = |a: u64, b: u64| Ok(a + b);
+
+        let error = reduce_lines(Cursor::new(input), line_sum, || 0, add).unwrap_err();
+        assert_eq!(error.to_string(), "can't solve line 2");
+        let error = par_reduce_lines(Cursor::new(input), 2, line_sum, || 0, add).unwrap_err();
+        assert_eq!(error.to_string(), "can't solve line 2");
+    }
+}
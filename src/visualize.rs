@@ -0,0 +1,312 @@
+use std::io::{stdout, Stdout, Write};
+use std::thread::sleep;
+use std::time::Duration;
+use anyhow::{ensure, Result};
+
+/// A colour as 8 bit red, green and blue. Terminals get it as a 24 bit colour escape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const GRAY: Color = Color::rgb(128, 128, 128);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    pub const RED: Color = Color::rgb(220, 50, 47);
+    pub const GREEN: Color = Color::rgb(80, 200, 80);
+    pub const YELLOW: Color = Color::rgb(250, 210, 50);
+    pub const BLUE: Color = Color::rgb(40, 110, 220);
+    pub const CYAN: Color = Color::rgb(60, 200, 210);
+    pub const MAGENTA: Color = Color::rgb(210, 60, 170);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Color { r, g, b }
+    }
+
+    fn ansi_foreground(&self) -> String {
+        format!("\x1b[38;2;{};{};{}m", self.r, self.g, self.b)
+    }
+
+    fn ansi_background(&self) -> String {
+        format!("\x1b[48;2;{};{};{}m", self.r, self.g, self.b)
+    }
+}
+
+/// Characters in rows of equal width, positions are `(x, y)` with `(0, 0)` at the top left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<char>,
+}
+
+impl Grid {
+    /// Shorter lines are padded with spaces.
+    pub fn from_lines<S: AsRef<str>>(lines: &[S]) -> Self {
+        let width = lines.iter().map(|line| line.as_ref().chars().count()).max().unwrap_or(0);
+        let mut cells = Vec::with_capacity(width * lines.len());
+        for line in lines {
+            let len_before = cells.len();
+            cells.extend(line.as_ref().chars());
+            cells.resize(len_before + width, ' ');
+        }
+        Grid { width, height: lines.len(), cells }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<char> {
+        (x < self.width && y < self.height).then(|| self.cells[y * self.width + x])
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, c: char) {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = c;
+        }
+    }
+}
+
+/// Drawn on top of a grid, later overlays win where they overlap.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Overlay {
+    /// Colours the characters of the cells
+    Highlight { cells: Vec<(usize, usize)>, color: Color },
+    /// Colours the background of the cells, in the order they are visited
+    Path { points: Vec<(usize, usize)>, color: Color },
+    /// Replaces the characters starting at `(x, y)` towards the right, cut off at the edge
+    Label { x: usize, y: usize, text: String, color: Color },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct Style {
+    pub(crate) foreground: Option<Color>,
+    pub(crate) background: Option<Color>,
+}
+
+/// A grid with overlays, ready to be rendered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub grid: Grid,
+    pub overlays: Vec<Overlay>,
+}
+
+impl Frame {
+    pub fn new(grid: Grid) -> Self {
+        Frame { grid, overlays: vec![] }
+    }
+
+    pub fn with(mut self, overlay: Overlay) -> Self {
+        self.overlays.push(overlay);
+        self
+    }
+
+    /// Every cell with its character and colours after applying all overlays.
+    pub(crate) fn styled_cells(&self) -> (Grid, Vec<Style>) {
+        let mut grid = self.grid.clone();
+        let mut styles = vec![Style::default(); grid.width * grid.height];
+        let style_at = |x: usize, y: usize| (x < grid.width && y < grid.height)
+            .then(|| y * grid.width + x);
+
+        for overlay in self.overlays.iter() {
+            match overlay {
+                Overlay::Highlight { cells, color } => {
+                    for idx in cells.iter().filter_map(|(x, y)| style_at(*x, *y)) {
+                        styles[idx].foreground = Some(*color);
+                    }
+                }
+                Overlay::Path { points, color } => {
+                    for idx in points.iter().filter_map(|(x, y)| style_at(*x, *y)) {
+                        styles[idx].background = Some(*color);
+                    }
+                }
+                Overlay::Label { x, y, text, color } => {
+                    for offset in 0..text.chars().count() {
+                        if let Some(idx) = style_at(x + offset, *y) {
+                            styles[idx].foreground = Some(*color);
+                        }
+                    }
+                }
+            }
+        }
+        for overlay in self.overlays.iter() {
+            if let Overlay::Label { x, y, text, .. } = overlay {
+                for (offset, c) in text.chars().enumerate() {
+                    grid.set(x + offset, *y, c);
+                }
+            }
+        }
+        (grid, styles)
+    }
+
+    /// Just the characters, labels included.
+    pub fn render_plain(&self) -> String {
+        let (grid, _) = self.styled_cells();
+        let mut rendered = String::with_capacity((grid.width + 1) * grid.height);
+        for row in grid.cells.chunks(grid.width.max(1)) {
+            rendered.extend(row.iter());
+            rendered.push('\n');
+        }
+        rendered
+    }
+
+    /// With colours, escape codes are only written where the style changes.
+    pub fn render_ansi(&self) -> String {
+        let (grid, styles) = self.styled_cells();
+        let mut rendered = String::new();
+        for y in 0..grid.height {
+            let mut current = Style::default();
+            for x in 0..grid.width {
+                let style = styles[y * grid.width + x];
+                if style != current {
+                    rendered.push_str("\x1b[0m");
+                    if let Some(color) = style.foreground {
+                        rendered.push_str(&color.ansi_foreground());
+                    }
+                    if let Some(color) = style.background {
+                        rendered.push_str(&color.ansi_background());
+                    }
+                    current = style;
+                }
+                rendered.push(grid.get(x, y).expect("inside the grid"));
+            }
+            if current != Style::default() {
+                rendered.push_str("\x1b[0m");
+            }
+            rendered.push('\n');
+        }
+        rendered
+    }
+}
+
+enum Mode {
+    /// Redraws the frame in place and waits
+    Live,
+    /// Records frames in the asciicast v2 format, one event per frame
+    Asciicast,
+}
+
+/// Shows frames one after another, either live in the terminal or recorded to an asciicast file
+/// that can be replayed with `asciinema play`.
+pub struct Visualizer<W: Write> {
+    out: W,
+    mode: Mode,
+    delay: Duration,
+    frames: usize,
+}
+
+impl Visualizer<Stdout> {
+    pub fn terminal(delay: Duration) -> Self {
+        Visualizer { out: stdout(), mode: Mode::Live, delay, frames: 0 }
+    }
+}
+
+impl<W: Write> Visualizer<W> {
+    /// Writes the asciicast header right away, the terminal size has to fit the largest frame.
+    pub fn asciicast(mut out: W, width: usize, height: usize, delay: Duration, title: &str) -> Result<Self> {
+        ensure!(width > 0 && height > 0, "a {}x{} terminal can't show anything", width, height);
+        writeln!(out, "{{\"version\": 2, \"width\": {}, \"height\": {}, \"title\": {}}}",
+            width, height, json_string(title))?;
+        Ok(Visualizer { out, mode: Mode::Asciicast, delay, frames: 0 })
+    }
+
+    pub fn show(&mut self, frame: &Frame) -> Result<()> {
+        let screen = format!("\x1b[2J\x1b[H{}", frame.render_ansi());
+        match self.mode {
+            Mode::Live => {
+                if self.frames > 0 {
+                    sleep(self.delay);
+                }
+                self.out.write_all(screen.as_bytes())?;
+                self.out.flush()?;
+            }
+            Mode::Asciicast => {
+                let time = self.delay.as_secs_f64() * self.frames as f64;
+                // asciinema expects CR LF line endings, as a real terminal would output
+                writeln!(self.out, "[{:.6}, \"o\", {}]", time, json_string(&screen.replace('\n', "\r\n")))?;
+            }
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    pub fn into_inner(mut self) -> Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 || c as u32 == 0x7f => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::visualize::{json_string, Color, Frame, Grid, Overlay, Visualizer};
+
+    fn frame() -> Frame {
+        Frame::new(Grid::from_lines(&["#..", "...", "..#x"]))
+    }
+
+    #[test]
+    fn grid_from_lines() {
+        let grid = Grid::from_lines(&["ab", "c"]);
+        assert_eq!((grid.width, grid.height), (2, 2));
+        assert_eq!(grid.get(1, 1), Some(' '));
+        assert_eq!(grid.get(2, 0), None);
+    }
+
+    #[test]
+    fn render_overlays() {
+        let frame = frame()
+            .with(Overlay::Path { points: vec![(1, 0), (1, 1)], color: Color::BLUE })
+            .with(Overlay::Highlight { cells: vec![(0, 0), (9, 9)], color: Color::RED })
+            .with(Overlay::Label { x: 2, y: 1, text: "ok".to_string(), color: Color::GREEN });
+
+        assert_eq!(frame.render_plain(), "#.. \n..ok\n..#x\n");
+        assert_eq!(frame.render_ansi(), concat!(
+            "\x1b[0m\x1b[38;2;220;50;47m#\x1b[0m\x1b[48;2;40;110;220m.\x1b[0m. \n",
+            ".\x1b[0m\x1b[48;2;40;110;220m.\x1b[0m\x1b[38;2;80;200;80mok\x1b[0m\n",
+            "..#x\n",
+        ));
+    }
+
+    #[test]
+    fn record_asciicast() {
+        let mut visualizer = Visualizer::asciicast(Vec::new(), 4, 3, Duration::from_millis(250), "test \"run\"").unwrap();
+        visualizer.show(&frame()).unwrap();
+        visualizer.show(&frame()).unwrap();
+        assert_eq!(visualizer.frames(), 2);
+
+        let recorded = String::from_utf8(visualizer.into_inner().unwrap()).unwrap();
+        let lines: Vec<&str> = recorded.lines().collect();
+        assert_eq!(lines[0], r#"{"version": 2, "width": 4, "height": 3, "title": "test \"run\""}"#);
+        assert_eq!(lines[1], r#"[0.000000, "o", "\u001b[2J\u001b[H#.. \r\n... \r\n..#x\r\n"]"#);
+        assert!(lines[2].starts_with("[0.250000, "));
+        assert!(Visualizer::asciicast(Vec::new(), 0, 3, Duration::ZERO, "").is_err());
+    }
+
+    #[test]
+    fn escape_json() {
+        assert_eq!(json_string("a\tb\\c\u{1}"), r#""a\tb\\c\u0001""#);
+    }
+}